@@ -0,0 +1,242 @@
+use std::path::Path;
+
+use crate::level::Anchor;
+use crate::tools::{CellPattern, Symmetry};
+use crate::vecmath::V2;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EditorCommand {
+    Write(Option<String>),
+    Edit { path: String, force: bool },
+    Quit { force: bool },
+    WriteQuit,
+    Resize(V2, Anchor),
+    Crop,
+    Goto(V2),
+    Fill,
+    Copy,
+    Move,
+    Find(CellPattern),
+    Replace(CellPattern, CellPattern),
+    TriggerAdd(String),
+    TriggerRemove,
+    /// Inserts the lines of a text file as letters at the cursor.
+    ReadText(String),
+    /// Right margin column for text mode, `None` wraps at the level border.
+    Margin(Option<i32>),
+    /// Takes over the level and player position of the last play-test.
+    Adopt,
+    SolutionSave,
+    SolutionClear,
+    /// Mirrors paint and text edits, `here` puts the axis through the cursor instead of the
+    /// level centre.
+    Symmetry { symmetry: Option<Symmetry>, here: bool, invert: bool },
+}
+
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+}
+
+pub const COMMANDS: [CommandInfo; 18] = [
+    CommandInfo { name: "w", usage: "w [path]", help: "save, optionally under a new path" },
+    CommandInfo { name: "e", usage: "e[!] path", help: "open another level" },
+    CommandInfo { name: "q", usage: "q[!]", help: "quit" },
+    CommandInfo { name: "wq", usage: "wq", help: "save and quit" },
+    CommandInfo { name: "resize", usage: "resize W H [anchor]", help: "change level size" },
+    CommandInfo { name: "crop", usage: "crop", help: "trim level to its content" },
+    CommandInfo { name: "goto", usage: "goto X Y", help: "move the cursor" },
+    CommandInfo { name: "fill", usage: "fill", help: "fill selection with its top-left cell" },
+    CommandInfo { name: "copy", usage: "copy", help: "copy selection to the cursor" },
+    CommandInfo { name: "move", usage: "move", help: "move selection to the cursor" },
+    CommandInfo { name: "find", usage: "find PATTERN", help: "jump to the next cell matching e.g. 'a bg=white'" },
+    CommandInfo { name: "replace", usage: "replace a b | replace PATTERN / PATTERN", help: "replace in selection or level" },
+    CommandInfo { name: "trigger", usage: "trigger add ID | trigger remove", help: "edit trigger at the cursor" },
+    CommandInfo { name: "r", usage: "r path", help: "insert a text file as letters at the cursor" },
+    CommandInfo { name: "margin", usage: "margin COLUMN | margin off", help: "wrap column for text mode" },
+    CommandInfo { name: "adopt", usage: "adopt", help: "use the last play-test state as the level" },
+    CommandInfo { name: "solution", usage: "solution save | solution clear", help: "store the last play-test moves as the reference solution" },
+    CommandInfo { name: "symmetry", usage: "symmetry off | symmetry MODE [here] [invert]", help: "mirror edits, MODE is vertical, horizontal or point" },
+];
+
+fn usage(name: &str) -> String {
+    let usage = COMMANDS.iter().find(|c| c.name == name).map_or(name, |c| c.usage);
+    format!("Usage: :{}", usage)
+}
+
+/// Either "a b" for plain letters or "<pattern> / <pattern>".
+fn parse_replace(args: &[&str]) -> Result<EditorCommand, String> {
+    let (from, to) = match args.iter().position(|arg| *arg == "/") {
+        Some(i) => (args[..i].join(" "), args[i + 1..].join(" ")),
+        None if args.len() == 2 && args.iter().all(|arg| arg.chars().count() == 1) => {
+            (args[0].to_string(), args[1].to_string())
+        }
+        None => return Err(usage("replace")),
+    };
+    Ok(EditorCommand::Replace(CellPattern::parse(&from)?, CellPattern::parse(&to)?))
+}
+
+/// Parses "<mode> [here] [invert]" with the options in any order.
+fn parse_symmetry(args: &[&str]) -> Option<EditorCommand> {
+    let (mode, options) = args.split_first()?;
+    if *mode == "off" && options.is_empty() {
+        return Some(EditorCommand::Symmetry { symmetry: None, here: false, invert: false });
+    }
+    if !options.iter().all(|option| *option == "here" || *option == "invert") {
+        return None;
+    }
+    Some(EditorCommand::Symmetry {
+        symmetry: Some(Symmetry::parse(mode)?),
+        here: options.contains(&"here"),
+        invert: options.contains(&"invert"),
+    })
+}
+
+/// Parses "<width> <height> [anchor]", anchor defaults to top-left.
+pub fn parse_resize(args: &[&str]) -> Option<(V2, Anchor)> {
+    let (width, height, anchor) = match args {
+        [w, h] => (w, h, Anchor::TopLeft),
+        [w, h, anchor] => (w, h, Anchor::parse(anchor)?),
+        _ => return None,
+    };
+    Some((V2::make(width.parse().ok()?, height.parse().ok()?), anchor))
+}
+
+pub fn parse_command(text: &str) -> Result<EditorCommand, String> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+    let (name, args) = match parts.split_first() {
+        Some((name, args)) => (*name, args),
+        None => return Err("Empty command".into()),
+    };
+    let command = match (name, args) {
+        ("w", []) => Some(EditorCommand::Write(None)),
+        ("w", [path]) => Some(EditorCommand::Write(Some(path.to_string()))),
+        ("e", [path]) => Some(EditorCommand::Edit { path: path.to_string(), force: false }),
+        ("e!", [path]) => Some(EditorCommand::Edit { path: path.to_string(), force: true }),
+        ("q", []) => Some(EditorCommand::Quit { force: false }),
+        ("q!", []) => Some(EditorCommand::Quit { force: true }),
+        ("wq", []) => Some(EditorCommand::WriteQuit),
+        ("resize", args) => parse_resize(args).map(|(size, anchor)| EditorCommand::Resize(size, anchor)),
+        ("crop", []) => Some(EditorCommand::Crop),
+        ("goto", [x, y]) => match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => Some(EditorCommand::Goto(V2::make(x, y))),
+            _ => None,
+        },
+        ("fill", []) => Some(EditorCommand::Fill),
+        ("copy", []) => Some(EditorCommand::Copy),
+        ("move", []) => Some(EditorCommand::Move),
+        ("find", []) => None,
+        ("find", args) => return CellPattern::parse(&args.join(" ")).map(EditorCommand::Find),
+        ("replace", args) => return parse_replace(args),
+        ("trigger", ["add", id]) => Some(EditorCommand::TriggerAdd(id.to_string())),
+        ("trigger", ["remove"]) => Some(EditorCommand::TriggerRemove),
+        ("r", [path]) => Some(EditorCommand::ReadText(path.to_string())),
+        ("margin", ["off"]) => Some(EditorCommand::Margin(None)),
+        ("adopt", []) => Some(EditorCommand::Adopt),
+        ("solution", ["save"]) => Some(EditorCommand::SolutionSave),
+        ("solution", ["clear"]) => Some(EditorCommand::SolutionClear),
+        ("symmetry", args) => parse_symmetry(args),
+        ("margin", [column]) => column.parse().ok().map(|column| EditorCommand::Margin(Some(column))),
+        _ => {
+            if !COMMANDS.iter().any(|c| c.name == name.trim_end_matches('!')) {
+                return Err(format!("Unknown command: {}", name));
+            }
+            None
+        }
+    };
+    command.ok_or_else(|| usage(name.trim_end_matches('!')))
+}
+
+fn complete_path(prefix: &str) -> Vec<String> {
+    let (dir, file_prefix) = match prefix.rfind(std::path::is_separator) {
+        Some(i) => (&prefix[..=i], &prefix[i + 1..]),
+        None => ("", prefix),
+    };
+    let read_from = if dir.is_empty() { Path::new(".") } else { Path::new(dir) };
+    let mut result: Vec<String> = std::fs::read_dir(read_from)
+        .map(|entries| entries.filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(file_prefix) {
+                return None;
+            }
+            let separator = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, separator))
+        }).collect())
+        .unwrap_or_default();
+    result.sort();
+    result
+}
+
+pub fn complete(text: &str) -> Vec<String> {
+    let words: Vec<&str> = text.split(' ').collect();
+    let (last, done) = match words.split_last() {
+        Some((last, done)) => (*last, done),
+        None => return vec![],
+    };
+    let candidates: Vec<String> = match done {
+        [] => COMMANDS.iter().map(|c| c.name.to_string()).collect(),
+        ["trigger"] => vec!["add".into(), "remove".into()],
+        ["trigger", "add"] => vec!["exit0".into(), "exit1".into(), "exit2".into()],
+        ["resize", _, _] => Anchor::NAMES.iter().map(|name| name.to_string()).collect(),
+        ["w" | "e" | "e!" | "r"] => complete_path(last),
+        ["margin"] => vec!["off".into()],
+        ["solution"] => vec!["save".into(), "clear".into()],
+        ["symmetry"] => ["off"].iter().chain(Symmetry::NAMES.iter()).map(|name| name.to_string()).collect(),
+        ["symmetry", mode, ..] if *mode != "off" => vec!["here".into(), "invert".into()],
+        _ => vec![],
+    };
+    let head: String = done.iter().map(|word| format!("{} ", word)).collect();
+    candidates.into_iter()
+        .filter(|candidate| candidate.starts_with(last))
+        .map(|candidate| format!("{}{}", head, candidate))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::CellColor;
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse_command("w"), Ok(EditorCommand::Write(None)));
+        assert_eq!(parse_command(" w  other.yaml "), Ok(EditorCommand::Write(Some("other.yaml".into()))));
+        assert_eq!(parse_command("e! l2"), Ok(EditorCommand::Edit { path: "l2".into(), force: true }));
+        assert_eq!(parse_command("resize 40 30 c"), Ok(EditorCommand::Resize(V2::make(40, 30), Anchor::Center)));
+        assert_eq!(parse_command("resize 40 30"), Ok(EditorCommand::Resize(V2::make(40, 30), Anchor::TopLeft)));
+        assert_eq!(parse_command("goto 3 -4"), Ok(EditorCommand::Goto(V2::make(3, -4))));
+        let letter = |c| CellPattern { letter: Some(c), ..CellPattern::default() };
+        assert_eq!(parse_command("replace a b"), Ok(EditorCommand::Replace(letter('a'), letter('b'))));
+        let gray = |c| CellPattern { background: Some(c), ..CellPattern::default() };
+        assert_eq!(parse_command("replace bg=dg / bg=lightgray"),
+                   Ok(EditorCommand::Replace(gray(CellColor::DarkGray), gray(CellColor::LightGray))));
+        assert_eq!(parse_command("find a"), Ok(EditorCommand::Find(letter('a'))));
+        assert_eq!(parse_command("find"), Err("Usage: :find PATTERN".into()));
+        assert_eq!(parse_command("trigger add door"), Ok(EditorCommand::TriggerAdd("door".into())));
+        assert_eq!(parse_command("resize 40"), Err("Usage: :resize W H [anchor]".into()));
+        assert_eq!(parse_command("replace ab c"), Err("Usage: :replace a b | replace PATTERN / PATTERN".into()));
+        assert_eq!(parse_command("margin 40"), Ok(EditorCommand::Margin(Some(40))));
+        assert_eq!(parse_command("margin off"), Ok(EditorCommand::Margin(None)));
+        assert_eq!(parse_command("margin x"), Err("Usage: :margin COLUMN | margin off".into()));
+        assert_eq!(parse_command("symmetry v invert"),
+                   Ok(EditorCommand::Symmetry { symmetry: Some(Symmetry::Vertical), here: false, invert: true }));
+        assert_eq!(parse_command("symmetry off"), Ok(EditorCommand::Symmetry { symmetry: None, here: false, invert: false }));
+        assert!(parse_command("symmetry point sideways").is_err());
+        assert!(parse_command("jump").is_err());
+        assert!(parse_command("").is_err());
+    }
+
+    #[test]
+    fn completion() {
+        assert_eq!(complete("re"), vec!["resize".to_owned(), "replace".to_owned()]);
+        assert_eq!(complete("trigger a"), vec!["trigger add".to_owned()]);
+        assert_eq!(complete("trigger add exit"), vec!["trigger add exit0".to_owned(),
+            "trigger add exit1".to_owned(), "trigger add exit2".to_owned()]);
+        assert_eq!(complete("resize 10 10 top-"), vec!["resize 10 10 top-left".to_owned(),
+            "resize 10 10 top-right".to_owned()]);
+        assert_eq!(complete("symmetry point i"), vec!["symmetry point invert".to_owned()]);
+        assert!(complete("goto 1").is_empty());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::level::{Cell, CellColor, Level, Trigger};
+use crate::vecmath::{Rectangle, V2};
+
+/// Group of touching changed cells.
+pub struct ChangedArea {
+    pub bounds: Rectangle,
+    /// Cells of the group in reading order, bounding boxes of different groups may overlap.
+    pub cells: Vec<V2>,
+}
+
+pub struct LevelDiff {
+    pub old_size: V2,
+    pub new_size: V2,
+    pub old_p0: V2,
+    pub new_p0: V2,
+    pub removed_triggers: Vec<Trigger>,
+    pub added_triggers: Vec<Trigger>,
+    pub solution_changed: bool,
+    /// Cells which differ, only the area covered by both levels is compared.
+    pub cells: Vec<V2>,
+    /// Groups of touching changed cells, in reading order of their first cell.
+    pub regions: Vec<ChangedArea>,
+}
+
+fn has_trigger(triggers: &[Trigger], trigger: &Trigger) -> bool {
+    triggers.iter().any(|t| t.pos == trigger.pos && t.id == trigger.id)
+}
+
+/// The 8-connected groups of `cells`, all of which are within `bounds`.
+fn group_cells(cells: &[V2], bounds: Rectangle) -> Vec<ChangedArea> {
+    let index = |p: V2| ((p.y - bounds.top()) * bounds.width() + p.x - bounds.left()) as usize;
+    let mut changed = vec![false; (bounds.width() * bounds.height()) as usize];
+    for pos in cells {
+        changed[index(*pos)] = true;
+    }
+    let mut result = vec![];
+    for start in cells {
+        if !changed[index(*start)] {
+            continue;
+        }
+        changed[index(*start)] = false;
+        let (mut min, mut max) = (*start, *start);
+        let mut members = vec![];
+        let mut stack = vec![*start];
+        while let Some(pos) = stack.pop() {
+            members.push(pos);
+            min = V2::make(min.x.min(pos.x), min.y.min(pos.y));
+            max = V2::make(max.x.max(pos.x), max.y.max(pos.y));
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let next = pos + V2::make(dx, dy);
+                    if bounds.contains(next) && changed[index(next)] {
+                        changed[index(next)] = false;
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        members.sort_by_key(|pos| (pos.y, pos.x));
+        result.push(ChangedArea { bounds: Rectangle::from_corners(min, max), cells: members });
+    }
+    result
+}
+
+/// Cell in the syntax of editor find patterns, e.g. "a fg=white bg=black".
+pub fn describe_cell(cell: &Cell) -> String {
+    let letter = if cell.empty() { "space".to_string() } else { cell.letter.to_string() };
+    let color = |c: CellColor| format!("{:?}", c).to_lowercase();
+    format!("{} fg={} bg={}", letter, color(cell.foreground), color(cell.background))
+}
+
+impl LevelDiff {
+    pub fn compare(old: &Level, new: &Level) -> LevelDiff {
+        let common = old.bounds().intersection(&new.bounds());
+        let mut cells = vec![];
+        for y in common.top()..=common.bottom() {
+            for x in common.left()..=common.right() {
+                let pos = V2::make(x, y);
+                if old[pos] != new[pos] {
+                    cells.push(pos);
+                }
+            }
+        }
+        LevelDiff {
+            old_size: old.size(),
+            new_size: new.size(),
+            old_p0: old.p0,
+            new_p0: new.p0,
+            removed_triggers: old.triggers.iter().filter(|t| !has_trigger(&new.triggers, t)).cloned().collect(),
+            added_triggers: new.triggers.iter().filter(|t| !has_trigger(&old.triggers, t)).cloned().collect(),
+            solution_changed: old.solution != new.solution,
+            regions: group_cells(&cells, common),
+            cells,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.old_size == self.new_size && self.old_p0 == self.new_p0 && self.removed_triggers.is_empty() &&
+            self.added_triggers.is_empty() && !self.solution_changed && self.cells.is_empty()
+    }
+
+    pub fn report(&self, old: &Level, new: &Level) -> Vec<String> {
+        let mut result = vec![];
+        if self.old_size != self.new_size {
+            result.push(format!("size: {}x{} -> {}x{}, only the common area is compared",
+                                self.old_size.x, self.old_size.y, self.new_size.x, self.new_size.y));
+        }
+        if self.old_p0 != self.new_p0 {
+            result.push(format!("start: {},{} -> {},{}", self.old_p0.x, self.old_p0.y, self.new_p0.x, self.new_p0.y));
+        }
+        for trigger in &self.removed_triggers {
+            result.push(format!("trigger removed: {} at {},{}", trigger.id, trigger.pos.x, trigger.pos.y));
+        }
+        for trigger in &self.added_triggers {
+            result.push(format!("trigger added: {} at {},{}", trigger.id, trigger.pos.x, trigger.pos.y));
+        }
+        if self.solution_changed {
+            result.push(format!("solution: {} -> {} moves", old.solution.len(), new.solution.len()));
+        }
+        if !self.cells.is_empty() {
+            result.push(format!("changed cells: {}, areas: {}", self.cells.len(), self.regions.len()));
+        }
+        for area in &self.regions {
+            let bounds = area.bounds;
+            result.push(format!("area {},{} {}x{}:", bounds.left(), bounds.top(), bounds.width(), bounds.height()));
+            for pos in &area.cells {
+                result.push(format!("  {},{}: {} -> {}", pos.x, pos.y, describe_cell(&old[*pos]), describe_cell(&new[*pos])));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(level: &mut Level, pos: V2, c: char) {
+        let mut cell = level[pos];
+        cell.letter = c;
+        level.set(pos, cell);
+    }
+
+    #[test]
+    fn changed_areas() {
+        let old = Level::new(10, 8);
+        let mut new = old.clone();
+        letter(&mut new, V2::make(1, 1), 'a');
+        letter(&mut new, V2::make(2, 2), 'b');
+        letter(&mut new, V2::make(7, 1), 'c');
+        letter(&mut new, V2::make(7, 3), 'd');
+        let diff = LevelDiff::compare(&old, &new);
+        assert_eq!(diff.cells.len(), 4);
+        let regions: Vec<_> = diff.regions.iter().map(|r| (r.bounds.pos, r.bounds.size)).collect();
+        assert_eq!(regions, vec![(V2::make(1, 1), V2::make(2, 2)), (V2::make(7, 1), V2::make(1, 1)),
+                                 (V2::make(7, 3), V2::make(1, 1))]);
+        assert!(LevelDiff::compare(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn overlapping_areas() {
+        let old = Level::new(10, 8);
+        let mut new = old.clone();
+        for i in 0..5 {
+            letter(&mut new, V2::make(i, i), 'a');
+        }
+        letter(&mut new, V2::make(4, 0), 'b');
+        let diff = LevelDiff::compare(&old, &new);
+        assert_eq!(diff.regions.len(), 2);
+        assert_eq!(diff.regions[0].cells.len(), 5);
+        assert_eq!(diff.regions[1].cells, vec![V2::make(4, 0)]);
+        let report = diff.report(&old, &new);
+        assert_eq!(report[0], "changed cells: 6, areas: 2");
+        assert_eq!(report.iter().filter(|line| line.starts_with("  ")).count(), 6);
+    }
+
+    #[test]
+    fn markers_and_size() {
+        let mut old = Level::new(10, 8);
+        old.triggers.push(Trigger { pos: V2::make(3, 3), id: "exit1".into() });
+        let mut new = old.clone();
+        new.resize_with_offset(V2::make(12, 8), V2::new());
+        new.p0 = V2::make(4, 4);
+        new.triggers[0].pos = V2::make(5, 3);
+        let mut cell = new[V2::make(0, 0)];
+        cell.background = CellColor::White;
+        new.set(V2::make(0, 0), cell);
+
+        let diff = LevelDiff::compare(&old, &new);
+        assert_eq!(diff.cells, vec![V2::make(0, 0)]);
+        let before = describe_cell(&old[V2::make(0, 0)]);
+        assert_eq!(diff.report(&old, &new), vec![
+            "size: 10x8 -> 12x8, only the common area is compared".to_string(),
+            "start: 2,2 -> 4,4".to_string(),
+            "trigger removed: exit1 at 3,3".to_string(),
+            "trigger added: exit1 at 5,3".to_string(),
+            "changed cells: 1, areas: 1".to_string(),
+            "area 0,0 1x1:".to_string(),
+            format!("  0,0: {} -> space fg=white bg=white", before),
+        ]);
+    }
+}
@@ -1,9 +1,10 @@
 use std::cmp::{max, min};
+use std::collections::HashSet;
 use std::io::{Error, ErrorKind, stderr, Write};
 use std::default::{self, Default};
-use std::fs::File;
 use std::ops::Mul;
-use std::path::{is_separator, Path};
+use std::path::{is_separator, Path, PathBuf};
+use std::time::{Duration, Instant};
 use crossterm::{
     cursor::{self, position},
     event::{DisableMouseCapture,
@@ -19,9 +20,20 @@ use crossterm::terminal::{Clear, ClearType};
 use level::Level;
 use ui::UiWidget;
 
+use serde::{Serialize, Deserialize};
+
 use crate::{level, ui, vecmath};
-use crate::level::{Cell, CellColor, LevelList, Trigger};
-use crate::ui::{UiContext, UiEvent, UiEventType, UiId};
+use crate::level::{Anchor, Cell, CellColor, LevelList, Stamp, Trigger};
+use crate::commands::{self, EditorCommand};
+use crate::diff::LevelDiff;
+use crate::history::{EditGroup, EditHistory};
+use crate::keymap::{self, Action, Key, KeyContext, Keymap};
+use crate::progress::Progress;
+use crate::settings::Settings;
+use crate::stats::{self, LevelStats, Stats};
+use crate::storage;
+use crate::tools::{self, CellPattern, Connectivity, FillMatch, Material, Shape, Symmetry, Transform};
+use crate::ui::{Menu, TextInput, TextView, UiContext, UiEvent, UiEventType, UiId};
 use crate::ui::UiEventType::Changed;
 use crate::vecmath::{Rectangle, V2};
 
@@ -37,9 +49,52 @@ pub struct LevelEditor
     path: Option<Box<std::path::Path>>,
     paintMode: PaintMode,
     test_runer: LevelRunner,
+    keymap: Keymap,
     show_triggers: bool,
     selection_rect: Rectangle,
     selecting_rect: bool,
+    selection_active: bool,
+    fill_connectivity: Connectivity,
+    /// Shape being drawn and its first corner, the second one is the cursor.
+    shape: Option<(Shape, V2)>,
+    shape_letter: Option<char>,
+    marker_id: String,
+    mouse_last: Option<V2>,
+    prompt: Option<(EditorPrompt, TextInput)>,
+    help: Option<TextView>,
+    show_marker_panel: bool,
+    marker_selected: usize,
+    /// Feedback for the last command, shown in the status bar until the next key press.
+    status_message: Option<String>,
+    clipboard: Option<Stamp>,
+    pasting: bool,
+    stamp_menu: Option<(Vec<String>, Menu)>,
+    show_minimap: bool,
+    layer_view: LayerView,
+    command_history: Vec<String>,
+    last_find: Option<CellPattern>,
+    text_insert: bool,
+    /// Last column used by text mode before wrapping to the next line.
+    text_margin: Option<i32>,
+    test_revision: Option<u64>,
+    symmetry: Option<Symmetry>,
+    /// Mirror axis in doubled coordinates, see `Symmetry::mirror`. `None` uses the level centre.
+    symmetry_axis: Option<V2>,
+    symmetry_invert: bool,
+    history: EditHistory,
+    /// Set when the current input event continued the open edit group.
+    edit_continued: bool,
+    saved_revision: u64,
+    autosaved_revision: u64,
+    last_autosave: Instant,
+}
+
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+fn autosave_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".autosave");
+    path.with_file_name(name)
 }
 
 fn buffer_size() -> (u16, u16)
@@ -76,6 +131,56 @@ enum EditorMode {
     Paint,
     SetMarkers,
     Play,
+    ConfirmQuit,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum EditorPrompt {
+    TriggerId,
+    Resize,
+    SaveStamp,
+    ShapeLetter,
+    Command,
+}
+
+const MARKER_PANEL_WIDTH: i32 = 30;
+/// Largest width and height of a level, keeps a typo in `:resize` from exhausting the memory.
+const MAX_LEVEL_SIZE: i32 = 2000;
+const MINIMAP_MAX_SIZE: V2 = V2 { x: 40, y: 15 };
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum LayerView {
+    All,
+    Background,
+    Letters,
+    Material,
+}
+
+impl LayerView {
+    fn next(&self) -> LayerView {
+        match self {
+            LayerView::All => LayerView::Background,
+            LayerView::Background => LayerView::Letters,
+            LayerView::Letters => LayerView::Material,
+            LayerView::Material => LayerView::All,
+        }
+    }
+}
+
+fn cell_style(cell: &Cell, layer: LayerView) -> (char, Color, Color) {
+    let letter = if cell.empty() { ' ' } else { cell.letter };
+    match layer {
+        LayerView::All => (letter, get_color(cell.foreground), get_color(cell.background)),
+        LayerView::Background => (' ', Color::Reset, get_color(cell.background)),
+        LayerView::Letters => (letter, get_color(cell.foreground), Color::DarkBlue),
+        LayerView::Material => match tools::material(cell) {
+            Material::Floor => (' ', Color::Reset, Color::Reset),
+            Material::Wall => (letter, Color::Black, Color::Grey),
+            Material::Decoration => (letter, Color::Grey, Color::Reset),
+            Material::Pushable => (letter, get_color(cell.foreground), Color::DarkGreen),
+            Material::Solid => (letter, Color::White, Color::DarkRed),
+        },
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
@@ -91,6 +196,10 @@ enum PaintMode {
 
 impl LevelEditor {
     pub fn new(ui: &mut UiContext) -> LevelEditor {
+        LevelEditor::with_keymap(ui, Keymap::load())
+    }
+
+    pub fn with_keymap(ui: &mut UiContext, (keymap, keymap_error): (Keymap, Option<String>)) -> LevelEditor {
         let mut result = LevelEditor {
             id: ui.next_id(),
             level: Level::new(250, 250),
@@ -101,11 +210,42 @@ impl LevelEditor {
             mode: EditorMode::View,
             path: None,
             paintMode: PaintMode::WhiteBackgroundNormal,
-            test_runer: LevelRunner::new(ui),
+            test_runer: LevelRunner::with_keymap(ui, (keymap.clone(), keymap_error.clone())),
+            keymap,
             show_triggers: true,
             selection_rect: Rectangle { pos: V2::make(0, 0), size: V2::make(1, 1) },
             selecting_rect: false,
+            selection_active: false,
+            fill_connectivity: Connectivity::Four,
+            shape: None,
+            shape_letter: None,
+            marker_id: "exit1".into(),
+            mouse_last: None,
+            prompt: None,
+            help: None,
+            show_marker_panel: false,
+            marker_selected: 0,
+            status_message: keymap_error,
+            clipboard: None,
+            pasting: false,
+            stamp_menu: None,
+            show_minimap: false,
+            layer_view: LayerView::All,
+            command_history: vec![],
+            last_find: None,
+            text_insert: false,
+            text_margin: None,
+            test_revision: None,
+            symmetry: None,
+            symmetry_axis: None,
+            symmetry_invert: false,
+            history: EditHistory::new(),
+            edit_continued: false,
+            saved_revision: 0,
+            autosaved_revision: 0,
+            last_autosave: Instant::now(),
         };
+        result.test_runer.help_contexts = keymap::TEST_KEYS;
         result.fill_level();
         result
     }
@@ -114,9 +254,7 @@ impl LevelEditor {
         let mut result = LevelEditor::new(ui);
         result.path = Some(path.into());
         if path.is_file() {
-            let file = std::fs::File::open(path)?;
-            let yaml: serde_yaml::Result<Level> = serde_yaml::from_reader(file);
-            match yaml {
+            match storage::load_yaml(path) {
                 Ok(res) => {
                     result.level = res
                 }
@@ -131,36 +269,235 @@ impl LevelEditor {
         Ok(result)
     }
 
-    pub fn save(&self) -> std::io::Result<()> {
-        match &self.path {
-            Some(path) => {
-                let mut ofile = File::create(path)?;
-                serde_yaml::to_writer(&ofile, &self.level).map_err(|e|
-                    {
-                        eprintln!("Can't save: {}", e);
-                        ErrorKind::InvalidData
-                    }
-                )?;
-                Ok(())
-            }
-            None => {
-                eprintln!("Can't save, no path specified");
-                Err(Error::from(ErrorKind::Other))
-            }
+    /// Replaces the edited level with another file, a new level is started if it doesn't exist.
+    fn open(&mut self, path: &Path) -> std::io::Result<()> {
+        if path.is_file() {
+            self.level = storage::load_yaml(path)?;
+        } else {
+            self.level = Level::new(250, 250);
+            self.fill_level();
+        }
+        self.path = Some(path.into());
+        self.history = EditHistory::new();
+        self.saved_revision = self.history.revision();
+        self.autosaved_revision = self.saved_revision;
+        self.cursor_pos = V2::new();
+        self.view_corner = V2::new();
+        self.selecting_rect = false;
+        self.selection_active = false;
+        self.pasting = false;
+        self.shape = None;
+        Ok(())
+    }
+
+    pub fn save(&mut self) -> std::io::Result<()> {
+        if self.path.is_none() {
+            eprintln!("Can't save, no path specified");
+            return Err(Error::from(ErrorKind::Other));
+        }
+        self.write_file().map_err(|e| {
+            eprintln!("Can't save: {}", e);
+            e
+        })
+    }
+
+    /// Same as `save` without printing errors, for use while the editor is on screen.
+    fn write_file(&mut self) -> std::io::Result<()> {
+        let path = self.path.clone().ok_or(ErrorKind::NotFound)?;
+        self.write_file_as(path)
+    }
+
+    /// Saves to `path` and continues editing that file, nothing changes if saving fails.
+    fn write_file_as(&mut self, path: Box<Path>) -> std::io::Result<()> {
+        self.history.commit(&self.level);
+        storage::save_yaml(&path, &self.level)?;
+        if self.path.as_ref() != Some(&path) {
+            self.remove_autosave();
+            self.path = Some(path);
+        }
+        self.saved_revision = self.history.revision();
+        self.remove_autosave();
+        Ok(())
+    }
+
+    pub fn autosave_newer(&self) -> bool {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return false,
+        };
+        let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+        match (modified(&autosave_path(path)), modified(path)) {
+            (Some(autosave), Some(level)) => autosave > level,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+
+    /// Replaces the level with the autosaved one, the change can be undone and counts as unsaved.
+    pub fn restore_autosave(&mut self) -> std::io::Result<()> {
+        let path = autosave_path(self.path.as_ref().ok_or(ErrorKind::NotFound)?);
+        let level = storage::load_yaml::<Level>(&path)?;
+        self.begin_edit(EditGroup::Single);
+        self.history.record_snapshot(&self.level);
+        self.level = level;
+        self.history.commit(&self.level);
+        Ok(())
+    }
+
+    fn autosave(&mut self) {
+        self.last_autosave = Instant::now();
+        let path = match &self.path {
+            Some(path) => autosave_path(path),
+            None => return,
+        };
+        let revision = self.history.revision();
+        if !self.has_unsaved_changes() ||
+            (revision == self.autosaved_revision && !self.history.has_uncommitted()) {
+            return;
+        }
+        if storage::save_yaml(&path, &self.level).is_ok() {
+            self.autosaved_revision = revision;
+        }
+    }
+
+    fn remove_autosave(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(autosave_path(path));
         }
+        self.autosaved_revision = self.history.revision();
     }
 
-    fn resize(&mut self, size: V2) {
+    fn resize(&mut self, size: V2, anchor: Anchor) {
         if size.x < 5 || size.y < 5 {
+            self.status_message = Some("Level must be at least 5x5".into());
+            return;
+        }
+        if size.x > MAX_LEVEL_SIZE || size.y > MAX_LEVEL_SIZE {
+            self.status_message = Some(format!("Level can't be larger than {0}x{0}", MAX_LEVEL_SIZE));
             return;
         }
-        let cell = Cell::make_empty();
-        self.level.data.resize(size.y as usize, vec![cell; size.x as usize]);
-        for line in &mut self.level.data {
-            line.resize(size.x as usize, cell);
+        let offset = anchor.offset(self.level.size(), size);
+        self.begin_edit(EditGroup::Single);
+        self.history.record_snapshot(&self.level);
+        self.level.resize_with_offset(size, offset);
+        self.cursor_pos = self.cursor_pos + offset;
+        self.keep_cursor_in_view();
+        if !self.level.contains(self.level.p0) {
+            self.status_message = Some("Level start is outside the level".into());
+        }
+    }
+
+    fn crop(&mut self) {
+        let bounds = match self.level.content_bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let size = V2::make(max(bounds.width(), 5), max(bounds.height(), 5));
+        let offset = V2::new() - bounds.pos;
+        self.begin_edit(EditGroup::Single);
+        self.history.record_snapshot(&self.level);
+        self.level.resize_with_offset(size, offset);
+        self.cursor_pos = self.cursor_pos + offset;
+        self.keep_cursor_in_view();
+        self.status_message = Some(format!("Cropped to {}x{}", size.x, size.y));
+    }
+
+    /// Starts or continues an undoable edit, all level modifications must happen within one.
+    fn begin_edit(&mut self, group: EditGroup) {
+        self.history.begin(group, &self.level);
+        if group != EditGroup::Single {
+            self.edit_continued = true;
+        }
+    }
+
+    fn set_cell(&mut self, pos: V2, cell: Cell) {
+        self.history.record_cell(&self.level, pos, cell);
+        self.level.set(pos, cell);
+    }
+
+    /// Paint and text input, repeated on the other side of the symmetry axis.
+    fn draw_cell(&mut self, pos: V2, cell: Cell) {
+        let mirrored = self.mirror_cell(pos, cell);
+        self.set_cell(pos, cell);
+        if let Some((pos, cell)) = mirrored {
+            self.set_cell(pos, cell);
+        }
+    }
+
+    fn symmetry_axis(&self) -> V2 {
+        self.symmetry_axis.unwrap_or(V2::make(self.level.width - 1, self.level.height - 1))
+    }
+
+    /// Change to repeat on the other side of the symmetry axis when `pos` becomes `cell`. Letters
+    /// are only copied when they change, colours only while painting.
+    fn mirror_cell(&self, pos: V2, cell: Cell) -> Option<(V2, Cell)> {
+        if self.mode != EditorMode::Paint && self.mode != EditorMode::WriteText {
+            return None;
+        }
+        let target = self.symmetry?.mirror(pos, self.symmetry_axis());
+        if target == pos || !self.level.contains(pos) || !self.level.contains(target) {
+            return None;
+        }
+        let mut result = self.level[target];
+        if cell.letter != self.level[pos].letter {
+            result.letter = cell.letter;
+        }
+        if self.mode == EditorMode::Paint {
+            let color = |c| if self.symmetry_invert { invert_color(c) } else { c };
+            result.background = color(cell.background);
+            result.foreground = color(cell.foreground);
+        }
+        Some((target, result))
+    }
+
+    fn set_symmetry(&mut self, symmetry: Option<Symmetry>) {
+        self.symmetry = symmetry;
+        self.status_message = Some(match symmetry {
+            Some(symmetry) => format!("Mirroring {:?}{}", symmetry,
+                if self.symmetry_invert { " with inverted colours" } else { "" }),
+            None => "Symmetry off".into(),
+        });
+    }
+
+    fn set_p0(&mut self, pos: V2) {
+        self.begin_edit(EditGroup::Single);
+        self.history.record_markers(&self.level);
+        self.level.p0 = pos;
+    }
+
+    fn remove_triggers_at(&mut self, pos: V2) {
+        self.begin_edit(EditGroup::Single);
+        self.history.record_markers(&self.level);
+        self.level.triggers.retain(|trigger| trigger.pos != pos);
+    }
+
+    fn place_trigger(&mut self, pos: V2, id: &str) {
+        self.remove_triggers_at(pos);
+        self.level.triggers.push(Trigger {
+            pos,
+            id: id.into(),
+        });
+    }
+
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.history.has_uncommitted() || self.history.revision() != self.saved_revision
+    }
+
+    fn try_quit(&mut self) -> Option<UiEvent> {
+        if self.has_unsaved_changes() {
+            self.mode = EditorMode::ConfirmQuit;
+            self.event(UiEventType::Changed)
+        } else {
+            self.event(UiEventType::Ok)
         }
-        self.level.width = size.x;
-        self.level.height = size.y;
+    }
+
+    fn undo(&mut self) {
+        self.history.undo(&mut self.level);
+    }
+
+    fn redo(&mut self) {
+        self.history.redo(&mut self.level);
     }
 
     fn fill_level(&mut self)
@@ -189,15 +526,46 @@ impl LevelEditor {
         let size = ui.buffer_size();
         queue!(ui.stdout, cursor::MoveTo(0, size.1 - 2),
                 style::ResetColor)?;
+        let name = self.path.as_ref()
+            .and_then(|path| path.file_name())
+            .map_or("[new]".into(), |name| name.to_string_lossy());
+        let modified = if self.has_unsaved_changes() { "*" } else { "" };
+        queue!(ui.stdout, style::Print(format!("{}{} ", name, modified)))?;
         queue!(ui.stdout, style::Print(format!("mode: {:?} ", self.mode)))?;
+        queue!(ui.stdout, style::Print(format!("undo: {} redo: {} ", self.history.undo_depth(), self.history.redo_depth())))?;
+        if self.layer_view != LayerView::All {
+            queue!(ui.stdout, style::Print(format!("layer: {:?} ", self.layer_view)))?;
+        }
+        if !self.level.solution.is_empty() {
+            queue!(ui.stdout, style::Print(format!("solution: {} moves ", self.level.solution.len())))?;
+        }
+        if let Some(message) = &self.status_message {
+            queue!(ui.stdout, style::PrintStyledContent(format!(" {} ", message).with(Color::Black).on(Color::Yellow)))?;
+        }
+        if let (Some(symmetry), EditorMode::Paint | EditorMode::WriteText) = (self.symmetry, self.mode) {
+            queue!(ui.stdout, style::Print(format!("symmetry: {:?}{} ", symmetry, if self.symmetry_invert { " inverted" } else { "" })))?;
+        }
+        if self.selection_active {
+            let rect = self.selection_rect.normalized();
+            queue!(ui.stdout, style::Print(format!("selection: {}x{} ", rect.width(), rect.height())))?;
+        }
         match self.mode {
             EditorMode::View => {
-                queue!(ui.stdout, style::Print(format!(" F2: view F3: text mode F4: corner F5: paint F6: markers F8: test F9: save [shift]+F8 test here " )))?;
-                queue!(ui.stdout, style::Print(format!(" shift+R -> resize level, [t]->toggle triggers, [m] select rect, k: copy selection here, l: move selection, 0: fill " )))?;
+                queue!(ui.stdout, style::Print(format!(" {}{}", self.keymap.describe(KeyContext::Shortcuts, &[Action::Help]), self.keymap.help(KeyContext::View))))?;
             }
             EditorMode::Paint => {
-                queue!(ui.stdout, style::Print(format!(" color: {:?} ", self.paintMode)))?;
-                queue!(ui.stdout, style::Print(format!(" [ZXCVBNM]->colors, [SPACE]->paint here, [WASD] paint in direction")))?;
+                queue!(ui.stdout, style::Print(format!(" color: {:?} fill: {:?} ", self.paintMode, self.fill_connectivity)))?;
+                if let Some(letter) = self.shape_letter {
+                    queue!(ui.stdout, style::Print(format!("shape letter: '{}' ", letter)))?;
+                }
+                if let Some((shape, _)) = self.shape {
+                    queue!(ui.stdout, style::Print(format!(" drawing {:?}: [{}/{}] apply [{}] cancel", shape,
+                        self.keymap.keys_text(KeyContext::Paint, Action::Confirm),
+                        self.keymap.keys_text(KeyContext::Paint, Action::PaintHere),
+                        self.keymap.keys_text(KeyContext::Paint, Action::Cancel))))?;
+                } else {
+                    queue!(ui.stdout, style::Print(format!(" {}{}", self.keymap.describe(KeyContext::Shortcuts, &[Action::Help]), self.keymap.help(KeyContext::Paint))))?;
+                }
             }
             EditorMode::SetMarkers => {
                 for trigger in &self.level.triggers {
@@ -205,7 +573,19 @@ impl LevelEditor {
                         queue!(ui.stdout, style::Print(format!(" here: {}", trigger.id)))?;
                     }
                 }
-                queue!(ui.stdout, style::Print(format!(" [z]->level start [vxc]->exits [t]-> toggle trigger drawing")))?;
+                queue!(ui.stdout, style::Print(format!(" marker: {} {}{}", self.marker_id,
+                    self.keymap.describe(KeyContext::Shortcuts, &[Action::Help]), self.keymap.help(KeyContext::Markers))))?;
+            }
+            EditorMode::WriteText => {
+                queue!(ui.stdout, style::Print(format!(" {} ", if self.text_insert { "insert" } else { "overwrite" })))?;
+                if let Some(margin) = self.text_margin {
+                    queue!(ui.stdout, style::Print(format!("margin: {} ", margin)))?;
+                }
+                queue!(ui.stdout, style::Print(format!(" {}:margin and :r FILE from view mode", self.keymap.help(KeyContext::Text))))?;
+            }
+            EditorMode::ConfirmQuit => {
+                queue!(ui.stdout, style::PrintStyledContent(
+                    " Unsaved changes! [s]ave and quit, [d]iscard and quit, [c]ancel ".with(Color::Black).on(Color::Yellow)))?;
             }
             _ => {}
         }
@@ -251,6 +631,44 @@ impl LevelEditor {
         }
     }
 
+    fn print_points(&mut self, ui: &mut UiContext, points: &[V2], c: Option<char>) -> std::io::Result<()> {
+        let visible_rect = self.get_view_rect();
+        for p in points {
+            if visible_rect.contains(*p) {
+                let cell = self.level[*p];
+                let c = c.unwrap_or(if cell.empty() { ' ' } else { cell.letter });
+                ui.goto(*p - self.view_corner)?;
+                queue!(ui.stdout, style::PrintStyledContent(style::style(c)
+                    .with(Color::Black)
+                    .on(Color::DarkRed)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_symmetry_axis(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        let symmetry = match self.symmetry {
+            Some(symmetry) if self.mode == EditorMode::Paint || self.mode == EditorMode::WriteText => symmetry,
+            _ => return Ok(()),
+        };
+        let axis = self.symmetry_axis();
+        let visible_rect = self.get_view_rect().intersection(&self.level.bounds());
+        for y in visible_rect.top()..=visible_rect.bottom() {
+            for x in visible_rect.left()..=visible_rect.right() {
+                let pos = V2::make(x, y);
+                if !symmetry.on_axis(pos, axis) {
+                    continue;
+                }
+                let (c, foreground, _) = cell_style(&self.level[pos], self.layer_view);
+                ui.goto(pos - self.view_corner)?;
+                queue!(ui.stdout, style::PrintStyledContent(style::style(c)
+                    .with(foreground)
+                    .on(Color::DarkCyan)))?;
+            }
+        }
+        Ok(())
+    }
+
     fn print_at(&self, ui: &mut UiContext, ps: V2, c: char, tColor: Option<Color>, bColor: Option<Color>) -> std::io::Result<()> {
         let visible_rect = self.get_view_rect();
         if !visible_rect.contains(ps) {
@@ -287,13 +705,10 @@ impl LevelEditor {
                     queue!(ui.stdout, cursor::MoveTo(x, y))?;
                     reposition = false;
                 }
-                let mut c = cell.letter;
-                if cell.empty() {
-                    c = ' '
-                }
+                let (c, foreground, background) = cell_style(&cell, self.layer_view);
                 queue!(ui.stdout, style::PrintStyledContent(style::style(c)
-                        .with(get_color(cell.foreground))
-                        .on(get_color(cell.background))))?;
+                        .with(foreground)
+                        .on(background)))?;
             }
         }
         self.print_rect(ui, Rectangle { pos: V2::make(-1, -1), size: V2::make(self.level.width + 2, 1) }, ' ');
@@ -301,9 +716,17 @@ impl LevelEditor {
         self.print_rect(ui, Rectangle { pos: V2::make(-1, -1), size: V2::make(1, self.level.height + 2) }, ' ');
         self.print_rect(ui, Rectangle { pos: V2::make(self.level.width, -1), size: V2::make(1, self.level.height + 2) }, ' ');
 
+        self.print_symmetry_axis(ui)?;
         if self.selecting_rect {
             self.print_rect2(ui, self.selection_rect.normalized(), '#');
         }
+        if self.pasting && self.mode == EditorMode::View {
+            self.print_paste_preview(ui)?;
+        }
+        if let (EditorMode::Paint, Some((shape, anchor))) = (self.mode, self.shape) {
+            let points = shape.points(anchor, self.cursor_pos);
+            self.print_points(ui, &points, self.shape_letter)?;
+        }
 
         if self.show_triggers {
             self.print_at(ui, self.level.p0, '$', Some(Color::DarkGreen), None);
@@ -313,6 +736,11 @@ impl LevelEditor {
         }
 
 
+        if self.marker_panel_visible() {
+            self.print_marker_panel(ui)?;
+        }
+        self.print_minimap(ui)?;
+
         self.print_status_bar(ui)?;
 
         if visible_rect.contains(self.cursor_pos) {
@@ -370,12 +798,64 @@ impl LevelEditor {
     }
 
     fn start_level_test(&mut self, pos: V2) {
+        self.history.commit(&self.level);
+        self.test_revision = Some(self.history.revision());
         self.test_runer.level = self.level.clone();
         self.test_runer.start();
-        self.test_runer.pos = pos;
+        self.test_runer.start_at(pos);
+        self.test_runer.status = format!("{}{}", self.keymap.help(KeyContext::Test), self.keymap.describe(KeyContext::Game, &[Action::Help]));
         self.mode = EditorMode::Play;
     }
 
+    fn changed_since(&self, revision: u64) -> bool {
+        revision != self.history.revision() || self.history.has_uncommitted()
+    }
+
+    /// Replaces the level with the current state of the last play-test, the player position
+    /// becomes the new start.
+    fn adopt_test_state(&mut self) {
+        match self.test_revision {
+            None => {
+                self.status_message = Some("No play-test to adopt".into());
+                return;
+            }
+            Some(revision) if self.changed_since(revision) => {
+                self.status_message = Some("Level changed since the play-test".into());
+                return;
+            }
+            Some(_) => {}
+        }
+        self.begin_edit(EditGroup::Single);
+        self.history.record_snapshot(&self.level);
+        self.level = self.test_runer.level.clone();
+        self.level.p0 = self.test_runer.pos;
+        self.level.solution.clear();
+        self.test_revision = None;
+        self.cursor_pos = self.level.p0;
+        self.keep_cursor_in_view();
+        self.status_message = Some("Adopted play-test state as the new start".into());
+    }
+
+    /// Stores the moves of the last play-test as the reference solution, only valid when the
+    /// moves since the last restart start at `p0` of the current level.
+    fn save_test_solution(&mut self) {
+        let moves = &self.test_runer.history;
+        self.status_message = Some(match self.test_revision {
+            None => "No play-test moves to save".into(),
+            Some(_) if self.test_runer.start_pos != self.level.p0 => "Solution must start at the level start, test with F8".into(),
+            Some(revision) if self.changed_since(revision) => "Level changed since the play-test".into(),
+            Some(_) if moves.is_empty() => "No moves recorded".into(),
+            Some(_) => {
+                let moves = moves.clone();
+                let message = format!("Saved solution with {} moves", moves.len());
+                self.begin_edit(EditGroup::Single);
+                self.history.record_snapshot(&self.level);
+                self.level.solution = moves;
+                message
+            }
+        });
+    }
+
     fn start_level_test_normal(&mut self) {
         self.start_level_test(self.level.p0);
     }
@@ -412,406 +892,1147 @@ impl LevelEditor {
                 cell.background = CellColor::DarkGray;
             }
         }
-        self.level.set(pos, cell);
+        self.draw_cell(pos, cell);
+    }
+
+    /// Paints the area connected to the cell at `pos`, limited by the active selection.
+    fn flood_fill(&mut self, pos: V2, matching: FillMatch) {
+        let bounds = if self.selection_active {
+            self.selection_rect.normalized()
+        } else {
+            self.level.bounds()
+        };
+        let cells = tools::flood_fill(&self.level, pos, bounds, matching, self.fill_connectivity);
+        self.begin_edit(EditGroup::Single);
+        for p in cells {
+            self.paint_cell_here(p);
+        }
+    }
+
+    fn start_shape(&mut self, shape: Shape) {
+        self.shape = Some((shape, self.cursor_pos));
+    }
+
+    fn finish_shape(&mut self) {
+        if let Some((shape, anchor)) = self.shape.take() {
+            self.begin_edit(EditGroup::Single);
+            for p in shape.points(anchor, self.cursor_pos) {
+                match self.shape_letter {
+                    Some(letter) => {
+                        let mut cell = self.level[p];
+                        cell.letter = letter;
+                        self.draw_cell(p, cell);
+                    }
+                    None => self.paint_cell_here(p),
+                }
+            }
+        }
     }
 
     fn move_and_paint(&mut self, dir: V2) {
+        self.begin_edit(EditGroup::PaintStroke);
         self.cursor_pos = self.cursor_pos + dir;
         self.paint_cell_here(self.cursor_pos);
     }
 
-    fn handle_test_play(&mut self, ev: Option<UiEvent>) -> Option<UiEvent> {
-        match ev {
-            Some(UiEvent { id: _, e: UiEventType::Canceled }) |
-            Some(UiEvent { id: _, e: UiEventType::Ok }) |
-            Some(UiEvent { id: _, e: UiEventType::Result(_) }) => {
-                self.mode = EditorMode::View;
-                self.event(UiEventType::Changed)
+    fn open_prompt(&mut self, kind: EditorPrompt, label: &str, text: &str, ui: &mut UiContext) {
+        self.prompt = Some((kind, TextInput::new(label, text, ui)));
+    }
+
+    fn handle_prompt(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
+        let (kind, prompt) = self.prompt.as_mut()?;
+        let kind = *kind;
+        match prompt.input(e, ui) {
+            Some(UiEvent { e: UiEventType::Result(value), .. }) => {
+                self.prompt = None;
+                if let Ok(text) = value.downcast::<String>() {
+                    if let Some(result) = self.apply_prompt(kind, text.trim()) {
+                        return Some(result);
+                    }
+                }
             }
-            _ => ev
+            Some(UiEvent { e: UiEventType::Canceled, .. }) => {
+                self.prompt = None;
+            }
+            _ => {}
         }
+        self.event(UiEventType::Changed)
     }
 
-    fn copy_rect(&mut self, rec: Rectangle, target: V2) {
-        let level_copy = self.level.clone();
-        for y in rec.top()..=rec.bottom() {
-            for x in rec.left()..=rec.right() {
-                let p1 = V2::make(x, y);
-                let c = level_copy[p1];
-                let p2 = p1 - rec.pos + target;
-                self.level.set(p2, c);
+    /// Returns an event when the editor should exit.
+    fn apply_prompt(&mut self, kind: EditorPrompt, text: &str) -> Option<UiEvent> {
+        match kind {
+            EditorPrompt::TriggerId => {
+                if !text.is_empty() {
+                    return self.run_command(EditorCommand::TriggerAdd(text.into()));
+                }
+            }
+            EditorPrompt::SaveStamp => {
+                if !text.is_empty() {
+                    self.save_stamp(text);
+                }
+            }
+            EditorPrompt::ShapeLetter => {
+                let mut chars = text.chars();
+                match (chars.next(), chars.next()) {
+                    (letter, None) => self.shape_letter = letter,
+                    _ => self.status_message = Some("Expected a single letter".into()),
+                }
+            }
+            EditorPrompt::Resize => {
+                match commands::parse_resize(&text.split_whitespace().collect::<Vec<_>>()) {
+                    Some((size, anchor)) => return self.run_command(EditorCommand::Resize(size, anchor)),
+                    None => {
+                        self.status_message = Some(format!("Expected: width height [{}]", Anchor::NAMES.join("|")));
+                    }
+                }
+            }
+            EditorPrompt::Command => {
+                if text.is_empty() {
+                    return None;
+                }
+                if self.command_history.last().map(|last| last.as_str()) != Some(text) {
+                    self.command_history.push(text.into());
+                }
+                match commands::parse_command(text) {
+                    Ok(command) => return self.run_command(command),
+                    Err(message) => self.status_message = Some(message),
+                }
             }
         }
+        None
     }
 
-    fn move_rect(&mut self, rec: Rectangle, target: V2) {
-        let level_copy = self.level.clone();
-        for y in rec.top()..=rec.bottom() {
-            for x in rec.left()..=rec.right() {
-                let p1 = V2::make(x, y);
-                self.paint_cell_here(p1);
-                let mut c = self.level[p1];
-                c.letter = ' ';
-                self.level.set(p1, c);
-            }
+    fn open_command_line(&mut self, text: &str, ui: &mut UiContext) {
+        let mut input = TextInput::new(":", text, ui);
+        input.set_history(self.command_history.clone());
+        input.set_completer(commands::complete);
+        self.prompt = Some((EditorPrompt::Command, input));
+    }
+
+    fn selection_or_level(&self) -> Rectangle {
+        if self.selection_active {
+            self.selection_rect.normalized()
+        } else {
+            self.level.bounds()
         }
-        for y in rec.top()..=rec.bottom() {
-            for x in rec.left()..=rec.right() {
-                let p1 = V2::make(x, y);
-                let c = level_copy[p1];
-                let p2 = p1 - rec.pos + target;
-                self.level.set(p2, c);
+    }
+
+    fn find(&mut self, pattern: CellPattern) {
+        let count = tools::find_matches(&self.level, &pattern, self.level.bounds()).len();
+        match tools::find_next(&self.level, &pattern, self.cursor_pos) {
+            Some(pos) => {
+                self.cursor_pos = pos;
+                self.keep_cursor_in_view();
+                self.status_message = Some(format!("{} matches", count));
             }
+            None => self.status_message = Some("No matches".into()),
         }
+        self.last_find = Some(pattern);
     }
 
-    fn fill_rect0(&mut self, rec: Rectangle) {
-        let c = self.level[rec.pos];
-        for y in rec.top()..=rec.bottom() {
-            for x in rec.left()..=rec.right() {
-                let p1 = V2::make(x, y);
-                self.level.set(p1, c);
-            }
+    fn replace(&mut self, from: CellPattern, to: CellPattern) {
+        let matches = tools::find_matches(&self.level, &from, self.selection_or_level());
+        self.begin_edit(EditGroup::Single);
+        for pos in &matches {
+            let cell = to.apply(&self.level[*pos]);
+            self.set_cell(*pos, cell);
         }
+        self.status_message = Some(format!("Replaced {} cells", matches.len()));
     }
-}
 
-fn letter_to_paintmode(c: char) -> PaintMode {
-    match c {
-        'z' => PaintMode::WhiteBackgroundNormal,
-        'x' => PaintMode::BlackBackgroundNormal,
-        'c' => PaintMode::Invert,
-        'v' => PaintMode::TextLightGray,
-        'b' => PaintMode::TextDarkGray,
-        'n' => PaintMode::BackgroundGray,
-        'm' => PaintMode::BackgroundDarkGray,
-        _ => PaintMode::WhiteBackgroundNormal
+    fn text_right_edge(&self) -> i32 {
+        self.text_margin.map_or(self.level.width - 1, |margin| min(margin, self.level.width - 1))
     }
-}
 
-impl UiWidget for LevelEditor {
-    fn print(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
-        if self.need_refresh() {
-            match self.mode {
-                EditorMode::ErrorMessage => {}
-                EditorMode::Play => {
-                    self.test_runer.print(ui);
-                }
-                _ => {
-                    queue!(ui.stdout, terminal::Clear(terminal::ClearType::All), style::ResetColor)?;
-                    self.print_level(ui)?;
-                    ui.stdout.flush()?
-                }
+    fn shift_letters(&mut self, from: V2, amount: i32) {
+        for (pos, letter) in tools::shift_letters(&self.level, from, self.text_right_edge(), amount) {
+            let mut cell = self.level[pos];
+            cell.letter = letter;
+            self.draw_cell(pos, cell);
+        }
+    }
+
+    fn new_text_line(&mut self) {
+        self.cursor_pos.x = self.wrap_pos.x;
+        self.cursor_pos.y += 1;
+        self.keep_cursor_in_view();
+    }
+
+    /// Writes a letter at the cursor keeping the cell colours, wraps at the right margin.
+    fn write_letter(&mut self, c: char) {
+        if self.text_insert {
+            self.shift_letters(self.cursor_pos, 1);
+        }
+        let mut data = self.level[self.cursor_pos];
+        data.letter = c;
+        self.draw_cell(self.cursor_pos, data);
+        self.cursor_pos.x += 1;
+        if self.cursor_pos.x > self.text_right_edge() {
+            self.new_text_line();
+        }
+    }
+
+    /// Removes the letter at `pos`, with `shift` the rest of the row moves left.
+    fn delete_letter(&mut self, pos: V2, shift: bool) {
+        if shift {
+            self.shift_letters(pos, -1);
+        } else {
+            let mut data = self.level[pos];
+            data.letter = '\0';
+            self.draw_cell(pos, data);
+        }
+    }
+
+    /// Writes a block of text as a single edit, lines start at the column of the cursor.
+    fn insert_text(&mut self, text: &str) {
+        self.begin_edit(EditGroup::Single);
+        self.wrap_pos = self.cursor_pos;
+        let mut wrapped = false;
+        for (i, line) in text.lines().enumerate() {
+            // a line filling the whole width has already moved to the next one
+            if i > 0 && !wrapped {
+                self.new_text_line();
             }
-            self.need_refresh = false;
+            for c in line.chars() {
+                self.write_letter(if c == '\t' { ' ' } else { c });
+            }
+            wrapped = !line.is_empty() && self.cursor_pos.x == self.wrap_pos.x;
         }
-        Ok(())
     }
 
-    fn input(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
-        self.mark_refresh(true);
-        match self.mode {
-            EditorMode::ErrorMessage => {
-                // press any key to exit error mode
-                return match e {
-                    Event::Key(_) => {
-                        self.switch_to_edit(ui);
-                        self.event(UiEventType::Changed)
-                    }
-                    _ => None
+    fn run_command(&mut self, command: EditorCommand) -> Option<UiEvent> {
+        match command {
+            EditorCommand::Write(path) => {
+                let result = match path {
+                    Some(path) => self.write_file_as(Path::new(&path).into()),
+                    None => self.write_file(),
                 };
+                self.status_message = Some(match result {
+                    Ok(_) => "Saved".into(),
+                    Err(e) => format!("Failed to save: {}", e),
+                });
+            }
+            EditorCommand::WriteQuit => {
+                match self.write_file() {
+                    Ok(_) => return self.event(UiEventType::Ok),
+                    Err(e) => self.status_message = Some(format!("Failed to save: {}", e)),
+                }
             }
-            EditorMode::Play => {
-                match e {
-                    Event::Key(KeyEvent { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }) => {
-                        self.mode = EditorMode::View;
-                        return self.event(UiEventType::Changed);
+            EditorCommand::Edit { path, force } => {
+                if self.has_unsaved_changes() && !force {
+                    self.status_message = Some("Unsaved changes, save them first or use :e!".into());
+                } else {
+                    if force {
+                        self.remove_autosave();
                     }
-                    _ => {
-                        let r = self.test_runer.input(e, ui);
-                        return self.handle_test_play(r);
+                    if let Err(e) = self.open(Path::new(&path)) {
+                        self.status_message = Some(format!("Failed to open '{}': {}", path, e));
                     }
                 }
             }
-            _ => {}
-        }
-        let v = match e {
-            Event::Key(KeyEvent { code: KeyCode::Up, modifiers: KeyModifiers::NONE }) => {
-                self.cursor_pos = self.cursor_pos + V2::make(0, -1);
-                self.keep_cursor_in_view();
-                self.event(UiEventType::Changed)
+            EditorCommand::Quit { force: true } => {
+                self.remove_autosave();
+                return self.event(UiEventType::Ok);
             }
-            Event::Key(KeyEvent { code: KeyCode::Down, modifiers: KeyModifiers::NONE }) => {
-                self.cursor_pos = self.cursor_pos + V2::make(0, 1);
+            EditorCommand::Quit { force: false } => return self.try_quit(),
+            EditorCommand::Resize(size, anchor) => self.resize(size, anchor),
+            EditorCommand::Crop => self.crop(),
+            EditorCommand::Goto(pos) => {
+                self.cursor_pos = pos;
                 self.keep_cursor_in_view();
-                self.event(UiEventType::Changed)
             }
-            Event::Key(KeyEvent { code: KeyCode::Left, modifiers: KeyModifiers::NONE }) => {
-                self.cursor_pos = self.cursor_pos + V2::make(-1, 0);
-                self.keep_cursor_in_view();
-                self.event(UiEventType::Changed)
+            EditorCommand::Fill => self.fill_rect0(self.selection_rect.normalized()),
+            EditorCommand::Copy => self.copy_rect(self.selection_rect.normalized(), self.cursor_pos),
+            EditorCommand::Move => self.move_rect(self.selection_rect.normalized(), self.cursor_pos),
+            EditorCommand::Find(pattern) => self.find(pattern),
+            EditorCommand::Replace(from, to) => self.replace(from, to),
+            EditorCommand::TriggerAdd(id) => {
+                self.place_trigger(self.cursor_pos, &id);
+                self.marker_id = id;
+            }
+            EditorCommand::TriggerRemove => self.remove_triggers_at(self.cursor_pos),
+            EditorCommand::ReadText(path) => {
+                match std::fs::read_to_string(&path) {
+                    Ok(text) => self.insert_text(&text),
+                    Err(e) => self.status_message = Some(format!("Failed to read '{}': {}", path, e)),
+                }
             }
-            Event::Key(KeyEvent { code: KeyCode::Right, modifiers: KeyModifiers::NONE }) => {
-                self.cursor_pos = self.cursor_pos + V2::make(1, 0);
-                self.keep_cursor_in_view();
-                self.event(UiEventType::Changed)
+            EditorCommand::Margin(margin) => {
+                self.text_margin = margin;
+                self.status_message = Some(match margin {
+                    Some(column) => format!("Text wraps after column {}", column),
+                    None => "Text wraps at the level border".into(),
+                });
+            }
+            EditorCommand::Adopt => self.adopt_test_state(),
+            EditorCommand::SolutionSave => self.save_test_solution(),
+            EditorCommand::SolutionClear if self.level.solution.is_empty() => {
+                self.status_message = Some("Level has no solution".into());
+            }
+            EditorCommand::SolutionClear => {
+                self.begin_edit(EditGroup::Single);
+                self.history.record_snapshot(&self.level);
+                self.level.solution.clear();
+                self.status_message = Some("Solution cleared".into());
+            }
+            EditorCommand::Symmetry { symmetry, here, invert } => {
+                self.symmetry_axis = if here { Some(self.cursor_pos + self.cursor_pos) } else { None };
+                self.symmetry_invert = invert;
+                self.set_symmetry(symmetry);
             }
+        }
+        None
+    }
 
-            Event::Mouse(MouseEvent { kind: MouseEventKind::Down(event::MouseButton::Left), column, row, modifiers: KeyModifiers::NONE }) => {
-                self.cursor_pos = self.view_corner + V2::from((*column, *row));
-                self.keep_cursor_in_view();
-                self.event(UiEventType::Changed)
+    /// Screen area of the minimap and level cells per minimap pixel. Each character shows two
+    /// pixels on top of each other.
+    fn minimap_layout(&self) -> Option<(Rectangle, i32)> {
+        if !self.show_minimap {
+            return None;
+        }
+        let screen = V2::from(buffer_size());
+        let max_size = V2::make(min(MINIMAP_MAX_SIZE.x, screen.x / 3), min(MINIMAP_MAX_SIZE.y, (screen.y - 3) / 2));
+        if max_size.x < 1 || max_size.y < 1 {
+            return None;
+        }
+        let scale = tools::minimap_scale(self.level.size(), V2::make(max_size.x, max_size.y * 2));
+        let pixels = V2::make((self.level.width + scale - 1) / scale, (self.level.height + scale - 1) / scale);
+        let size = V2::make(pixels.x, (pixels.y + 1) / 2);
+        let mut right = screen.x;
+        if self.marker_panel_visible() {
+            right -= MARKER_PANEL_WIDTH;
+        }
+        Some((Rectangle { pos: V2::make(max(0, right - size.x), 0), size }, scale))
+    }
+
+    fn minimap_pixel_color(&self, pixel: V2, scale: i32, view: Rectangle) -> Color {
+        let block = Rectangle { pos: V2::make(pixel.x * scale, pixel.y * scale), size: V2::make(scale, scale) };
+        if !self.level.contains(block.pos) {
+            return Color::DarkRed;
+        }
+        if self.show_triggers {
+            if self.level.triggers.iter().any(|trigger| block.contains(trigger.pos)) {
+                return Color::Red;
+            }
+            if block.contains(self.level.p0) {
+                return Color::Green;
             }
+        }
+        let view_pixels = Rectangle::from_corners(
+            V2::make(view.left().div_euclid(scale), view.top().div_euclid(scale)),
+            V2::make(view.right().div_euclid(scale), view.bottom().div_euclid(scale)));
+        let on_view_edge = view_pixels.contains(pixel) &&
+            (pixel.x == view_pixels.left() || pixel.x == view_pixels.right() ||
+                pixel.y == view_pixels.top() || pixel.y == view_pixels.bottom());
+        if on_view_edge {
+            return Color::Blue;
+        }
+        get_color(tools::dominant_background(&self.level, block))
+    }
 
-            Event::Key(KeyEvent { code: KeyCode::F(2), modifiers: KeyModifiers::NONE }) => {
-                self.mode = EditorMode::View;
+    fn print_minimap(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        let (rect, scale) = match self.minimap_layout() {
+            Some(layout) => layout,
+            None => return Ok(()),
+        };
+        let view = self.get_view_rect();
+        for row in 0..rect.height() {
+            ui.goto(rect.pos + V2::make(0, row))?;
+            for x in 0..rect.width() {
+                let top = self.minimap_pixel_color(V2::make(x, row * 2), scale, view);
+                let bottom = self.minimap_pixel_color(V2::make(x, row * 2 + 1), scale, view);
+                queue!(ui.stdout, style::PrintStyledContent('▀'.with(top).on(bottom)))?;
+            }
+        }
+        Ok(())
+    }
 
-                self.event(UiEventType::Changed)
+    fn minimap_click(&mut self, screen_pos: V2) -> bool {
+        match self.minimap_layout() {
+            Some((rect, scale)) if rect.contains(screen_pos) => {
+                let local = screen_pos - rect.pos;
+                let target = V2::make(local.x * scale, local.y * 2 * scale);
+                let view_size = V2::from(buffer_size());
+                self.view_corner = target - V2::make(view_size.x / 2, view_size.y / 2);
+                true
             }
-            Event::Key(KeyEvent { code: KeyCode::F(3), modifiers: KeyModifiers::NONE }) => {
-                self.mode = EditorMode::WriteText;
-                self.wrap_pos = self.cursor_pos;
-                self.event(UiEventType::Changed)
+            _ => false
+        }
+    }
+
+    fn marker_panel_visible(&self) -> bool {
+        self.show_marker_panel && self.mode == EditorMode::SetMarkers
+    }
+
+    fn select_marker(&mut self, index: usize) {
+        if let Some(trigger) = self.level.triggers.get(index) {
+            self.marker_selected = index;
+            self.cursor_pos = trigger.pos;
+            self.keep_cursor_in_view();
+        }
+    }
+
+    fn cycle_marker(&mut self, step: isize) {
+        let count = self.level.triggers.len() as isize;
+        if count > 0 {
+            let next = (self.marker_selected as isize + step).rem_euclid(count);
+            self.select_marker(next as usize);
+        }
+    }
+
+    fn print_marker_panel(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        let size = V2::from(ui.buffer_size());
+        let left = max(0, size.x - MARKER_PANEL_WIDTH);
+        let width = (size.x - left) as usize;
+        let rows = max(1, size.y - 3) as usize;
+        let issues = tools::trigger_issues(&self.level);
+        self.marker_selected = min(self.marker_selected, self.level.triggers.len().saturating_sub(1));
+        let scroll = (self.marker_selected + 1).saturating_sub(rows);
+
+        let header = format!(" Triggers: {}", self.level.triggers.len());
+        ui.goto(V2::make(left, 0))?;
+        queue!(ui.stdout, style::PrintStyledContent(format!("{:<width$}", header, width = width)
+            .with(Color::White).on(Color::DarkBlue)))?;
+        for row in 0..rows {
+            let index = scroll + row;
+            let (text, color) = match (self.level.triggers.get(index), issues.get(index)) {
+                (Some(trigger), Some(issue)) => {
+                    let marker = if index == self.marker_selected { ">" } else { " " };
+                    let mut text = format!("{} {} ({}, {})", marker, trigger.id, trigger.pos.x, trigger.pos.y);
+                    let color = if issue.out_of_bounds {
+                        text.push_str(" out!");
+                        Color::Red
+                    } else if issue.duplicate {
+                        text.push_str(" dup!");
+                        Color::Yellow
+                    } else {
+                        Color::White
+                    };
+                    (text, color)
+                }
+                _ => (String::new(), Color::White),
+            };
+            let text: String = text.chars().take(width).collect();
+            ui.goto(V2::make(left, 1 + row as i32))?;
+            queue!(ui.stdout, style::PrintStyledContent(format!("{:<width$}", text, width = width)
+                .with(color).on(Color::DarkBlue)))?;
+        }
+        Ok(())
+    }
+
+    fn handle_mouse(&mut self, m: &MouseEvent) -> Option<UiEvent> {
+        let screen_pos = V2::from((m.column, m.row));
+        let pos = self.view_corner + screen_pos;
+        let shift = m.modifiers.contains(KeyModifiers::SHIFT);
+        if let MouseEventKind::Down(event::MouseButton::Left) = m.kind {
+            if self.minimap_click(screen_pos) {
+                return self.event(UiEventType::Changed);
             }
-            Event::Key(KeyEvent { code: KeyCode::F(4), modifiers: KeyModifiers::NONE }) => {
-                self.wrap_pos = self.cursor_pos;
-                self.event(UiEventType::Changed)
+        }
+        let panel_left = buffer_size().0 as i32 - MARKER_PANEL_WIDTH;
+        if self.marker_panel_visible() && screen_pos.x >= panel_left {
+            if let MouseEventKind::Down(event::MouseButton::Left) = m.kind {
+                let rows = max(1, buffer_size().1 as i32 - 3) as usize;
+                let scroll = (self.marker_selected + 1).saturating_sub(rows);
+                if screen_pos.y > 0 {
+                    self.select_marker(scroll + screen_pos.y as usize - 1);
+                }
+                return self.event(UiEventType::Changed);
             }
-            Event::Key(KeyEvent { code: KeyCode::F(5), modifiers: KeyModifiers::NONE }) => {
-                self.mode = EditorMode::Paint;
-                self.event(UiEventType::Changed)
+            return None;
+        }
+        match m.kind {
+            MouseEventKind::Down(event::MouseButton::Left) if shift && !self.level.contains(pos) => {
+                self.status_message = Some("Triggers must be inside the level".into());
+            }
+            MouseEventKind::Down(event::MouseButton::Left) if shift => {
+                let id = self.marker_id.clone();
+                self.place_trigger(pos, &id);
+            }
+            MouseEventKind::Down(event::MouseButton::Right) if shift => {
+                self.remove_triggers_at(pos);
+            }
+            MouseEventKind::Down(event::MouseButton::Left) => {
+                self.cursor_pos = pos;
+                if self.mode == EditorMode::Paint {
+                    self.begin_edit(EditGroup::MouseStroke);
+                    self.paint_cell_here(pos);
+                }
             }
-            Event::Key(KeyEvent { code: KeyCode::F(6), modifiers: KeyModifiers::NONE }) => {
-                self.mode = EditorMode::SetMarkers;
-                self.event(UiEventType::Changed)
+            MouseEventKind::Drag(event::MouseButton::Left) => {
+                let last = self.mouse_last.map_or(pos, |p| p + self.view_corner);
+                self.cursor_pos = pos;
+                if self.mode == EditorMode::Paint && last != pos {
+                    self.begin_edit(EditGroup::MouseStroke);
+                    // previous position is already painted
+                    for p in tools::line_points(last, pos).into_iter().skip(1) {
+                        self.paint_cell_here(p);
+                    }
+                }
             }
-            Event::Key(KeyEvent { code: KeyCode::F(8), modifiers: KeyModifiers::NONE }) => {
-                self.start_level_test_normal();
-                self.event(UiEventType::Changed)
+            MouseEventKind::Down(event::MouseButton::Right) => {
+                self.cursor_pos = pos;
+                self.selecting_rect = true;
+                self.selection_active = false;
+                self.selection_rect = Rectangle { pos, size: V2::make(1, 1) };
             }
-            Event::Key(KeyEvent { code: KeyCode::F(8), modifiers: KeyModifiers::SHIFT }) => {
-                self.start_level_test(self.cursor_pos);
-                self.event(UiEventType::Changed)
+            MouseEventKind::Drag(event::MouseButton::Right) if self.selecting_rect => {
+                self.cursor_pos = pos;
+                self.selection_rect.size = pos - self.selection_rect.pos + V2::make(1, 1);
             }
-            Event::Key(KeyEvent { code: KeyCode::F(9), modifiers: KeyModifiers::NONE }) => {
-                self.switch_to_err(ui);
-                match self.save() {
-                    Ok(_) => {
-                        self.show_err(ui, "Saved!");
-                    }
-                    Err(_) => {
-                        self.show_err(ui, "Failed to save");
-                    }
+            MouseEventKind::Up(event::MouseButton::Right) if self.selecting_rect => {
+                self.selecting_rect = false;
+                self.selection_active = true;
+                self.selection_rect = self.selection_rect.normalized();
+            }
+            MouseEventKind::Drag(event::MouseButton::Middle) => {
+                if let Some(last) = self.mouse_last {
+                    self.view_corner = self.view_corner + last - screen_pos;
                 }
+            }
+            MouseEventKind::ScrollUp if shift => self.view_corner.x -= 4,
+            MouseEventKind::ScrollDown if shift => self.view_corner.x += 4,
+            MouseEventKind::ScrollUp => self.view_corner.y -= 3,
+            MouseEventKind::ScrollDown => self.view_corner.y += 3,
+            MouseEventKind::Down(_) | MouseEventKind::Up(_) => {}
+            _ => return None,
+        }
+        self.mouse_last = match m.kind {
+            MouseEventKind::Down(_) | MouseEventKind::Drag(_) => Some(screen_pos),
+            _ => None,
+        };
+        self.event(UiEventType::Changed)
+    }
 
-
+    fn handle_test_play(&mut self, ev: Option<UiEvent>) -> Option<UiEvent> {
+        match ev {
+            Some(UiEvent { id: _, e: UiEventType::Canceled }) |
+            Some(UiEvent { id: _, e: UiEventType::Ok }) |
+            Some(UiEvent { id: _, e: UiEventType::Result(_) }) => {
+                self.mode = EditorMode::View;
                 self.event(UiEventType::Changed)
             }
-            _ => None
+            _ => ev
+        }
+    }
+
+    fn copy_rect(&mut self, rec: Rectangle, target: V2) {
+        self.begin_edit(EditGroup::Single);
+        let level_copy = self.level.clone();
+        for y in rec.top()..=rec.bottom() {
+            for x in rec.left()..=rec.right() {
+                let p1 = V2::make(x, y);
+                let c = level_copy[p1];
+                let p2 = p1 - rec.pos + target;
+                self.set_cell(p2, c);
+            }
+        }
+    }
+
+    fn move_rect(&mut self, rec: Rectangle, target: V2) {
+        self.begin_edit(EditGroup::Single);
+        let level_copy = self.level.clone();
+        for y in rec.top()..=rec.bottom() {
+            for x in rec.left()..=rec.right() {
+                let p1 = V2::make(x, y);
+                self.paint_cell_here(p1);
+                let mut c = self.level[p1];
+                c.letter = ' ';
+                self.set_cell(p1, c);
+            }
+        }
+        for y in rec.top()..=rec.bottom() {
+            for x in rec.left()..=rec.right() {
+                let p1 = V2::make(x, y);
+                let c = level_copy[p1];
+                let p2 = p1 - rec.pos + target;
+                self.set_cell(p2, c);
+            }
+        }
+    }
+
+    fn transform_rect(&mut self, rec: Rectangle, transform: Transform) {
+        if transform == Transform::Rotate && rec.width() != rec.height() {
+            self.status_message = Some("Only square selections can be rotated".into());
+            return;
+        }
+        self.begin_edit(EditGroup::Single);
+        let level_copy = self.level.clone();
+        for y in rec.top()..=rec.bottom() {
+            for x in rec.left()..=rec.right() {
+                let p1 = V2::make(x, y);
+                self.set_cell(transform.apply(rec, p1), level_copy[p1]);
+            }
+        }
+        self.history.record_markers(&self.level);
+        if rec.contains(self.level.p0) {
+            self.level.p0 = transform.apply(rec, self.level.p0);
+        }
+        for trigger in &mut self.level.triggers {
+            if rec.contains(trigger.pos) {
+                trigger.pos = transform.apply(rec, trigger.pos);
+            }
+        }
+    }
+
+    fn copy_to_clipboard(&mut self, rec: Rectangle) {
+        self.clipboard = Some(Stamp::copy_from(&self.level, rec));
+        self.status_message = Some(format!("Copied {}x{}", rec.width(), rec.height()));
+    }
+
+    fn start_paste(&mut self) {
+        if self.clipboard.is_some() {
+            self.pasting = true;
+        } else {
+            self.status_message = Some("Clipboard is empty".into());
+        }
+    }
+
+    fn paste(&mut self, target: V2) {
+        self.pasting = false;
+        let stamp = match self.clipboard.take() {
+            Some(stamp) => stamp,
+            None => return,
         };
-        if v.is_some() { return v; }
+        self.begin_edit(EditGroup::Single);
+        for (p, cell) in stamp.cells() {
+            self.set_cell(target + p, cell);
+        }
+        let triggers: Vec<_> = stamp.triggers.iter()
+            .filter(|t| self.level.contains(target + t.pos))
+            .collect();
+        if !triggers.is_empty() {
+            self.history.record_markers(&self.level);
+            for trigger in triggers {
+                let pos = target + trigger.pos;
+                self.level.triggers.retain(|t| t.pos != pos);
+                self.level.triggers.push(Trigger { pos, id: trigger.id.clone() });
+            }
+        }
+        self.clipboard = Some(stamp);
+    }
 
-        if self.mode != EditorMode::WriteText && self.mode != EditorMode::Paint {
-            let v = match e {
-                Event::Key(KeyEvent { code: KeyCode::Char('w'), modifiers: KeyModifiers::NONE }) => {
-                    self.view_corner = self.view_corner + V2::make(0, -1);
-                    self.event(UiEventType::Changed)
-                }
-                Event::Key(KeyEvent { code: KeyCode::Char('s'), modifiers: KeyModifiers::NONE }) => {
-                    self.view_corner = self.view_corner + V2::make(0, 1);
-                    self.event(UiEventType::Changed)
-                }
-                Event::Key(KeyEvent { code: KeyCode::Char('a'), modifiers: KeyModifiers::NONE }) => {
-                    self.view_corner = self.view_corner + V2::make(-1, 0);
-                    self.event(UiEventType::Changed)
+    /// Stamps are shared by all the levels in the same folder.
+    fn stamp_dir(&self) -> Option<PathBuf> {
+        let parent = self.path.as_ref()?.parent()?;
+        Some(parent.join("stamps"))
+    }
+
+    fn save_stamp(&mut self, name: &str) {
+        let stamp = Stamp::copy_from(&self.level, self.selection_rect.normalized());
+        let result = match self.stamp_dir() {
+            Some(dir) => storage::save_yaml(&dir.join(format!("{}.yaml", storage::safe_file_name(name))), &stamp),
+            None => Err(ErrorKind::NotFound.into()),
+        };
+        self.status_message = Some(match result {
+            Ok(_) => format!("Saved stamp '{}'", name),
+            Err(e) => format!("Failed to save stamp: {}", e),
+        });
+    }
+
+    fn list_stamps(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.stamp_dir()
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .map(|entries| entries.filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension()? != "yaml" {
+                    return None;
                 }
-                Event::Key(KeyEvent { code: KeyCode::Char('d'), modifiers: KeyModifiers::NONE }) => {
-                    self.view_corner = self.view_corner + V2::make(1, 0);
-                    self.event(UiEventType::Changed)
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            }).collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    fn open_stamp_menu(&mut self, ui: &mut UiContext) {
+        let names = self.list_stamps();
+        if names.is_empty() {
+            self.status_message = Some("No stamps saved yet, use shift+Y to save the selection".into());
+            return;
+        }
+        let mut menu = Menu::new(names.clone(), true, ui);
+        menu.set_title("Insert stamp");
+        menu.set_overlay(true);
+        let size = ui.buffer_size();
+        menu.resize(&Rectangle { pos: V2::new(), size: V2::from(size) });
+        self.stamp_menu = Some((names, menu));
+    }
+
+    fn handle_stamp_menu(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
+        let (names, menu) = self.stamp_menu.as_mut()?;
+        match menu.input(e, ui) {
+            Some(UiEvent { e: UiEventType::Result(index), .. }) => {
+                let name = index.downcast::<usize>().ok().and_then(|index| names.get(*index).cloned());
+                self.stamp_menu = None;
+                if let (Some(name), Some(dir)) = (name, self.stamp_dir()) {
+                    match storage::load_yaml::<Stamp>(&dir.join(format!("{}.yaml", name))) {
+                        Ok(stamp) => {
+                            self.clipboard = Some(stamp);
+                            self.pasting = true;
+                        }
+                        Err(e) => self.status_message = Some(format!("Failed to load stamp: {}", e)),
+                    }
                 }
-                _ => None
-            };
-            if v.is_some() {
-                return v;
             }
+            Some(UiEvent { e: UiEventType::Canceled, .. }) => {
+                self.stamp_menu = None;
+            }
+            _ => {}
         }
-        if self.mode != EditorMode::WriteText {
-            let v = match e {
-                Event::Key(KeyEvent { code: KeyCode::Char('t'), modifiers: KeyModifiers::NONE }) => {
-                    self.show_triggers = !self.show_triggers;
-                    self.event(UiEventType::Changed)
-                }
-                _ => None
-            };
-            if v.is_some() {
-                return v;
+        self.event(UiEventType::Changed)
+    }
+
+    fn print_paste_preview(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        let stamp = match &self.clipboard {
+            Some(stamp) => stamp,
+            None => return Ok(()),
+        };
+        let visible_rect = self.get_view_rect();
+        for (p, cell) in stamp.cells() {
+            let pos = self.cursor_pos + p;
+            if visible_rect.contains(pos) {
+                ui.goto(pos - self.view_corner)?;
+                let c = if cell.empty() { ' ' } else { cell.letter };
+                queue!(ui.stdout, style::PrintStyledContent(style::style(c)
+                    .with(get_color(cell.foreground))
+                    .on(get_color(cell.background))
+                    .attribute(Attribute::Underlined)))?;
+            }
+        }
+        for trigger in &stamp.triggers {
+            let pos = self.cursor_pos + trigger.pos;
+            if visible_rect.contains(pos) {
+                ui.goto(pos - self.view_corner)?;
+                queue!(ui.stdout, style::PrintStyledContent('?'.with(Color::Red).attribute(Attribute::Underlined)))?;
             }
         }
+        Ok(())
+    }
 
+    /// Swaps black and white in the area, turning a level half into its dual.
+    fn invert_rect(&mut self, rec: Rectangle) {
+        self.begin_edit(EditGroup::Single);
+        for y in rec.top()..=rec.bottom() {
+            for x in rec.left()..=rec.right() {
+                let p1 = V2::make(x, y);
+                let mut c = self.level[p1];
+                c.background = invert_color(c.background);
+                c.foreground = invert_color(c.foreground);
+                self.set_cell(p1, c);
+            }
+        }
+    }
 
-        let v = match self.mode {
-            EditorMode::View => {
-                match e {
-                    Event::Key(KeyEvent { code: KeyCode::Char('e'), modifiers: KeyModifiers::NONE }) => {
-                        self.mode = EditorMode::WriteText;
-                        self.wrap_pos = self.cursor_pos;
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('R'), modifiers: KeyModifiers::NONE }) |
-                    Event::Key(KeyEvent { code: KeyCode::Char('R'), modifiers: KeyModifiers::SHIFT }) |
-                    Event::Key(KeyEvent { code: KeyCode::Char('r'), modifiers: KeyModifiers::SHIFT }) => {
-                        self.resize(self.cursor_pos);
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('m'), modifiers: KeyModifiers::NONE }) if !self.selecting_rect => {
-                        self.selecting_rect = true;
-                        self.selection_rect.pos = self.cursor_pos;
-                        self.selection_rect.size = V2::make(1, 1);
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Enter, modifiers: KeyModifiers::NONE }) |
-                    Event::Key(KeyEvent { code: KeyCode::Char('m'), modifiers: KeyModifiers::NONE }) |
-                    Event::Key(KeyEvent { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }) if self.selecting_rect => {
-                        self.selecting_rect = false;
-                        self.selection_rect = self.selection_rect.normalized();
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('k'), modifiers: KeyModifiers::NONE }) => {
-                        self.copy_rect(self.selection_rect.normalized(), self.cursor_pos);
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('l'), modifiers: KeyModifiers::NONE }) => {
-                        self.move_rect(self.selection_rect.normalized(), self.cursor_pos);
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('0'), modifiers: KeyModifiers::NONE }) => {
-                        self.fill_rect0(self.selection_rect.normalized());
+    fn fill_rect0(&mut self, rec: Rectangle) {
+        self.begin_edit(EditGroup::Single);
+        let c = self.level[rec.pos];
+        for y in rec.top()..=rec.bottom() {
+            for x in rec.left()..=rec.right() {
+                let p1 = V2::make(x, y);
+                self.set_cell(p1, c);
+            }
+        }
+    }
+}
+
+impl LevelEditor {
+    fn handle_input(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
+        self.mark_refresh(true);
+        if let Event::Key(_) = e {
+            self.status_message = None;
+        }
+        match self.mode {
+            EditorMode::ErrorMessage => {
+                // press any key to exit error mode
+                return match e {
+                    Event::Key(_) => {
+                        self.switch_to_edit(ui);
                         self.event(UiEventType::Changed)
                     }
                     _ => None
+                };
+            }
+            EditorMode::Play => {
+                if self.test_runer.showing_help() {
+                    return self.test_runer.input(e, ui);
                 }
+                if let Some(result) = self.keymap.action(self.key_contexts(), e).and_then(|action| self.handle_action(action, ui)) {
+                    return Some(result);
+                }
+                let r = self.test_runer.input(e, ui);
+                return self.handle_test_play(r);
             }
-            EditorMode::WriteText => {
-                match e {
-                    Event::Key(KeyEvent {
-                                   code: KeyCode::Enter, modifiers: KeyModifiers::NONE
-                               }) => {
-                        self.cursor_pos.x = self.wrap_pos.x;
-                        self.cursor_pos.y += 1;
-                        self.event(UiEventType::Changed)
+            EditorMode::ConfirmQuit => {
+                return match e {
+                    Event::Key(KeyEvent { code: KeyCode::Char('s'), modifiers: KeyModifiers::NONE }) => {
+                        let _ = self.switch_to_err(ui);
+                        match self.save() {
+                            Ok(_) => self.event(UiEventType::Ok),
+                            Err(_) => {
+                                let _ = self.show_err(ui, "Failed to save");
+                                self.event(UiEventType::Changed)
+                            }
+                        }
                     }
+                    Event::Key(KeyEvent { code: KeyCode::Char('d'), modifiers: KeyModifiers::NONE }) => {
+                        self.remove_autosave();
+                        self.event(UiEventType::Ok)
+                    }
+                    Event::Key(KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::NONE }) |
                     Event::Key(KeyEvent { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }) => {
                         self.mode = EditorMode::View;
                         self.event(UiEventType::Changed)
                     }
-                    Event::Key(KeyEvent { code: KeyCode::Backspace, modifiers: KeyModifiers::NONE }) |
-                    Event::Key(KeyEvent { code: KeyCode::Char('h'), modifiers: KeyModifiers::CONTROL }) => {
-                        self.cursor_pos.x -= 1;
-                        let mut data = self.level[self.cursor_pos];
-                        data.letter = '\0';
-                        self.level.set(self.cursor_pos, data);
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char(c), modifiers: m }) if
-                    !c.is_control() && (m == &KeyModifiers::NONE || m == &KeyModifiers::SHIFT) => {
-                        let mut data = self.level[self.cursor_pos];
-                        data.letter = *c;
-                        self.level.set(self.cursor_pos, data);
-                        self.cursor_pos.x += 1;
-                        self.event(UiEventType::Changed)
-                    }
                     _ => None
-                }
+                };
             }
-            EditorMode::Paint => {
-                match e {
-                    Event::Key(KeyEvent { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }) => {
-                        self.mode = EditorMode::View;
-                        self.event(UiEventType::Changed)
-                    }
+            _ => {}
+        }
+        if self.help.is_some() {
+            return self.handle_help(e, ui);
+        }
+        if self.prompt.is_some() {
+            return self.handle_prompt(e, ui);
+        }
+        if self.stamp_menu.is_some() {
+            return self.handle_stamp_menu(e, ui);
+        }
+        if let Event::Mouse(m) = e {
+            if matches!(self.mode, EditorMode::View | EditorMode::Paint | EditorMode::SetMarkers) {
+                return self.handle_mouse(m);
+            }
+            if let (EditorMode::WriteText, MouseEventKind::Down(event::MouseButton::Left)) = (self.mode, m.kind) {
+                self.cursor_pos = self.view_corner + V2::from((m.column, m.row));
+                self.keep_cursor_in_view();
+                return self.event(UiEventType::Changed);
+            }
+            return None;
+        }
+        // characters are always typed in text mode, even if a shortcut uses them elsewhere
+        let typing = self.mode == EditorMode::WriteText && matches!(e, Event::Key(key) if Key::from_event(key).is_printable());
+        if !typing {
+            if let Some(result) = self.keymap.action(self.key_contexts(), e).and_then(|action| self.handle_action(action, ui)) {
+                return Some(result);
+            }
+        }
+        match (self.mode, e) {
+            (EditorMode::WriteText, Event::Key(KeyEvent { code: KeyCode::Char(c), modifiers: m })) if
+            !c.is_control() && (m == &KeyModifiers::NONE || m == &KeyModifiers::SHIFT) => {
+                self.begin_edit(EditGroup::Text);
+                self.write_letter(*c);
+                self.event(UiEventType::Changed)
+            }
+            _ => None
+        }
+    }
 
-                    Event::Key(KeyEvent { code: KeyCode::Char('w'), modifiers: KeyModifiers::NONE }) => {
-                        self.move_and_paint(V2::make(0, -1));
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('s'), modifiers: KeyModifiers::NONE }) => {
-                        self.move_and_paint(V2::make(0, 1));
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('a'), modifiers: KeyModifiers::NONE }) => {
-                        self.move_and_paint(V2::make(-1, 0));
-                        self.event(UiEventType::Changed)
-                    }
-                    Event::Key(KeyEvent { code: KeyCode::Char('d'), modifiers: KeyModifiers::NONE }) => {
-                        self.move_and_paint(V2::make(1, 0));
-                        self.event(UiEventType::Changed)
-                    }
+    fn open_help(&mut self, ui: &mut UiContext) {
+        let mut lines = self.keymap.help_lines(self.key_contexts());
+        if matches!(self.mode, EditorMode::View | EditorMode::Paint | EditorMode::SetMarkers) {
+            lines.extend(["", "Mouse",
+                "  left click/drag     move cursor, paint lines in paint mode",
+                "  right drag          select",
+                "  middle drag/wheel   pan, shift+wheel pans sideways",
+                "  shift+left click    place marker",
+                "  shift+right click   remove markers",
+            ].iter().map(|line| line.to_string()));
+        }
+        if self.mode != EditorMode::WriteText {
+            lines.push(String::new());
+            lines.push("Commands".into());
+            let width = commands::COMMANDS.iter().map(|c| c.usage.chars().count()).max().unwrap_or(0) + 1;
+            lines.extend(commands::COMMANDS.iter()
+                .map(|c| format!("  {:<width$}  {}", format!(":{}", c.usage), c.help, width = width)));
+        }
+        let mut help = TextView::new(&format!("Help: {:?} mode", self.mode), lines, ui);
+        let size = ui.buffer_size();
+        help.resize(&Rectangle { pos: V2::new(), size: V2::from(size) });
+        self.help = Some(help);
+    }
 
-                    Event::Key(KeyEvent { code: KeyCode::Char(c @ ('z' | 'x' | 'c' | 'v' | 'b' | 'n' | 'm')), modifiers: KeyModifiers::NONE }) => {
-                        self.paintMode = letter_to_paintmode(*c);
-                        self.event(UiEventType::Changed)
-                    }
+    fn handle_help(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
+        if !matches!(e, Event::Key(_)) {
+            return None;
+        }
+        let toggled = self.keymap.action(self.key_contexts(), e) == Some(Action::Help);
+        let help = self.help.as_mut()?;
+        if toggled || matches!(help.input(e, ui), Some(UiEvent { e: UiEventType::Canceled, .. })) {
+            self.help = None;
+        }
+        self.event(UiEventType::Changed)
+    }
 
-                    Event::Key(KeyEvent { code: KeyCode::Char(' '), modifiers: KeyModifiers::NONE }) => {
-                        self.paint_cell_here(self.cursor_pos);
-                        self.event(UiEventType::Changed)
+    /// Key bindings active in the current mode, most specific first.
+    fn key_contexts(&self) -> &'static [KeyContext] {
+        match self.mode {
+            EditorMode::View => keymap::VIEW_KEYS,
+            EditorMode::WriteText => keymap::TEXT_KEYS,
+            EditorMode::Paint => keymap::PAINT_KEYS,
+            EditorMode::SetMarkers => keymap::MARKER_KEYS,
+            EditorMode::Play => &[KeyContext::Test],
+            EditorMode::ErrorMessage | EditorMode::ConfirmQuit => &[],
+        }
+    }
+
+    fn move_cursor(&mut self, dir: V2) {
+        self.cursor_pos = self.cursor_pos + dir;
+        self.keep_cursor_in_view();
+    }
+
+    /// Runs a bound action, returns `None` when it doesn't apply in the current state.
+    fn handle_action(&mut self, action: Action, ui: &mut UiContext) -> Option<UiEvent> {
+        match (self.mode, action) {
+            (_, Action::CursorUp) => self.move_cursor(V2::make(0, -1)),
+            (_, Action::CursorDown) => self.move_cursor(V2::make(0, 1)),
+            (_, Action::CursorLeft) => self.move_cursor(V2::make(-1, 0)),
+            (_, Action::CursorRight) => self.move_cursor(V2::make(1, 0)),
+            (_, Action::Undo) => self.undo(),
+            (_, Action::Redo) => self.redo(),
+            (_, Action::Quit) => return self.try_quit(),
+            (_, Action::Help) => self.open_help(ui),
+            (_, Action::ViewMode) => self.mode = EditorMode::View,
+            (_, Action::TextMode | Action::EditText) => {
+                self.mode = EditorMode::WriteText;
+                self.wrap_pos = self.cursor_pos;
+            }
+            (_, Action::SetCorner) => self.wrap_pos = self.cursor_pos,
+            (_, Action::PaintMode) => self.mode = EditorMode::Paint,
+            (_, Action::MarkerMode) => self.mode = EditorMode::SetMarkers,
+            (_, Action::CycleLayer) => self.layer_view = self.layer_view.next(),
+            (_, Action::ToggleMinimap) => self.show_minimap = !self.show_minimap,
+            (_, Action::Test) => self.start_level_test_normal(),
+            (_, Action::TestHere) => self.start_level_test(self.cursor_pos),
+            (_, Action::Save) => {
+                self.switch_to_err(ui);
+                match self.save() {
+                    Ok(_) => {
+                        self.show_err(ui, "Saved!");
+                    }
+                    Err(_) => {
+                        self.show_err(ui, "Failed to save");
                     }
-                    _ => None
                 }
             }
-            EditorMode::SetMarkers => {
-                match e {
-                    Event::Key(KeyEvent { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }) => {
-                        self.mode = EditorMode::View;
-                        self.event(UiEventType::Changed)
-                    }
 
-                    Event::Key(KeyEvent { code: KeyCode::Char('z'), modifiers: KeyModifiers::NONE }) => {
-                        self.level.p0 = self.cursor_pos;
-                        self.event(UiEventType::Changed)
-                    }
+            (_, Action::PanUp) => self.view_corner = self.view_corner + V2::make(0, -1),
+            (_, Action::PanDown) => self.view_corner = self.view_corner + V2::make(0, 1),
+            (_, Action::PanLeft) => self.view_corner = self.view_corner + V2::make(-1, 0),
+            (_, Action::PanRight) => self.view_corner = self.view_corner + V2::make(1, 0),
+            (_, Action::ToggleTriggers) => self.show_triggers = !self.show_triggers,
+            (_, Action::CommandLine) => self.open_command_line("", ui),
+            (_, Action::Find) => self.open_command_line("find ", ui),
+
+            (EditorMode::View, Action::Confirm | Action::Paste) if self.pasting => self.paste(self.cursor_pos),
+            (EditorMode::View, Action::Cancel) if self.pasting => self.pasting = false,
+            (EditorMode::View, Action::Select) if !self.selecting_rect => {
+                self.selecting_rect = true;
+                self.selection_rect.pos = self.cursor_pos;
+                self.selection_rect.size = V2::make(1, 1);
+            }
+            (EditorMode::View, Action::Confirm | Action::Select | Action::Cancel) if self.selecting_rect => {
+                self.selecting_rect = false;
+                self.selection_active = true;
+                self.selection_rect = self.selection_rect.normalized();
+            }
+            (EditorMode::View, Action::Cancel) if self.selection_active => self.selection_active = false,
+            (_, Action::FindNext) => {
+                match self.last_find {
+                    Some(pattern) => self.find(pattern),
+                    None => self.status_message = Some("Nothing to find, use / first".into()),
+                }
+            }
+            (_, Action::Copy) => self.copy_to_clipboard(self.selection_rect.normalized()),
+            (EditorMode::WriteText, Action::Paste) => match &self.clipboard {
+                Some(stamp) => {
+                    let text = stamp.text();
+                    self.insert_text(&text);
+                }
+                None => self.status_message = Some("Clipboard is empty, copy a selection in view mode".into()),
+            },
+            (_, Action::Paste) => self.start_paste(),
+            (_, Action::SaveStamp) => self.open_prompt(EditorPrompt::SaveStamp, "stamp name: ", "", ui),
+            (_, Action::InsertStamp) => self.open_stamp_menu(ui),
+            (_, Action::Resize) => {
+                let text = format!("{} {} top-left", self.level.width, self.level.height);
+                self.open_prompt(EditorPrompt::Resize, "resize (width height anchor): ", &text, ui);
+            }
+            (_, Action::Crop) => {
+                self.run_command(EditorCommand::Crop);
+            }
+            (_, Action::CopySelection) => {
+                self.run_command(EditorCommand::Copy);
+            }
+            (_, Action::MoveSelection) => {
+                self.run_command(EditorCommand::Move);
+            }
+            (_, Action::FillSelection) => {
+                self.run_command(EditorCommand::Fill);
+            }
+            (_, Action::FlipHorizontal) => self.transform_rect(self.selection_rect.normalized(), Transform::FlipHorizontal),
+            (_, Action::FlipVertical) => self.transform_rect(self.selection_rect.normalized(), Transform::FlipVertical),
+            (_, Action::Rotate) => self.transform_rect(self.selection_rect.normalized(), Transform::Rotate),
+            (_, Action::Invert) => self.invert_rect(self.selection_rect.normalized()),
+
+            (_, Action::NewLine) => {
+                if self.history.open_group() == Some(EditGroup::Text) {
+                    self.edit_continued = true;
+                }
+                self.new_text_line();
+            }
+            (_, Action::ToggleInsert) => self.text_insert = !self.text_insert,
+            (_, Action::LineStart) => {
+                self.cursor_pos.x = self.wrap_pos.x;
+                self.keep_cursor_in_view();
+            }
+            (_, Action::LineEnd) => {
+                // after the last letter of the row
+                let right = self.text_right_edge();
+                let y = self.cursor_pos.y;
+                self.cursor_pos.x = (self.wrap_pos.x..=right).rev()
+                    .find(|x| !self.level[V2::make(*x, y)].empty())
+                    .map_or(self.wrap_pos.x, |x| min(x + 1, right));
+                self.keep_cursor_in_view();
+            }
+            (_, Action::DeleteLetter) => {
+                self.begin_edit(EditGroup::Text);
+                self.delete_letter(self.cursor_pos, true);
+            }
+            (EditorMode::WriteText, Action::Backspace) if self.cursor_pos.x > self.wrap_pos.x.max(0) => {
+                self.begin_edit(EditGroup::Text);
+                self.cursor_pos.x -= 1;
+                self.delete_letter(self.cursor_pos, self.text_insert);
+            }
+
+            (EditorMode::Paint, Action::Confirm | Action::PaintHere) if self.shape.is_some() => self.finish_shape(),
+            (EditorMode::Paint, Action::Cancel) if self.shape.is_some() => self.shape = None,
+            (_, Action::Line) => self.start_shape(Shape::Line),
+            (_, Action::Rectangle) => self.start_shape(Shape::Rectangle),
+            (_, Action::Ellipse) => self.start_shape(Shape::Ellipse),
+            (_, Action::ShapeLetter) => {
+                let letter = self.shape_letter.or(Some(self.level[self.cursor_pos].letter).filter(|c| *c != '\0'));
+                let text = letter.map(String::from).unwrap_or_default();
+                self.open_prompt(EditorPrompt::ShapeLetter, "shape letter (empty to paint): ", &text, ui);
+            }
+            (_, Action::PaintUp) => self.move_and_paint(V2::make(0, -1)),
+            (_, Action::PaintDown) => self.move_and_paint(V2::make(0, 1)),
+            (_, Action::PaintLeft) => self.move_and_paint(V2::make(-1, 0)),
+            (_, Action::PaintRight) => self.move_and_paint(V2::make(1, 0)),
+            (_, Action::PaintWhite) => self.paintMode = PaintMode::WhiteBackgroundNormal,
+            (_, Action::PaintBlack) => self.paintMode = PaintMode::BlackBackgroundNormal,
+            (_, Action::PaintInvert) => self.paintMode = PaintMode::Invert,
+            (_, Action::PaintLightGrayText) => self.paintMode = PaintMode::TextLightGray,
+            (_, Action::PaintDarkGrayText) => self.paintMode = PaintMode::TextDarkGray,
+            (_, Action::PaintGray) => self.paintMode = PaintMode::BackgroundGray,
+            (_, Action::PaintDarkGray) => self.paintMode = PaintMode::BackgroundDarkGray,
+            (_, Action::PaintHere) => {
+                self.begin_edit(EditGroup::Single);
+                self.paint_cell_here(self.cursor_pos);
+            }
+            (_, Action::FillBackground) => self.flood_fill(self.cursor_pos, FillMatch::Background),
+            (_, Action::FillAll) => self.flood_fill(self.cursor_pos, FillMatch::BackgroundAndForeground),
+            (_, Action::FillConnectivity) => {
+                self.fill_connectivity = match self.fill_connectivity {
+                    Connectivity::Four => Connectivity::Eight,
+                    Connectivity::Eight => Connectivity::Four,
+                };
+            }
+            (_, Action::CycleSymmetry) => self.set_symmetry(Symmetry::cycle(self.symmetry)),
+            (_, Action::SymmetryInvert) => {
+                self.symmetry_invert = !self.symmetry_invert;
+                self.status_message = Some(if self.symmetry_invert { "Mirrored copy gets inverted colours" } else { "Mirrored copy keeps the colours" }.into());
+            }
 
-                    Event::Key(KeyEvent { code: KeyCode::Backspace, modifiers: KeyModifiers::NONE }) |
-                    Event::Key(KeyEvent { code: KeyCode::Char('h'), modifiers: KeyModifiers::CONTROL }) => {
-                        self.level.triggers.retain(|trigger| trigger.pos != self.cursor_pos);
-                        self.event(UiEventType::Changed)
-                    }
+            (_, Action::SetStart) => self.set_p0(self.cursor_pos),
+            (_, Action::RemoveTriggers) => self.remove_triggers_at(self.cursor_pos),
+            (_, Action::PlaceExit0) => {
+                self.marker_id = "exit0".into();
+                self.place_trigger(self.cursor_pos, "exit0");
+            }
+            (_, Action::PlaceExit1) => {
+                self.marker_id = "exit1".into();
+                self.place_trigger(self.cursor_pos, "exit1");
+            }
+            (_, Action::PlaceExit2) => {
+                self.marker_id = "exit2".into();
+                self.place_trigger(self.cursor_pos, "exit2");
+            }
+            (_, Action::TriggerId) => {
+                let id = self.marker_id.clone();
+                self.open_prompt(EditorPrompt::TriggerId, "trigger id: ", &id, ui);
+            }
+            (_, Action::TriggerPanel) => self.show_marker_panel = !self.show_marker_panel,
+            (_, Action::NextTrigger) => self.cycle_marker(1),
+            (_, Action::PreviousTrigger) => self.cycle_marker(-1),
 
-                    Event::Key(KeyEvent { code: KeyCode::Char('x'), modifiers: KeyModifiers::NONE }) => {
-                        self.level.triggers.retain(|trigger| trigger.pos != self.cursor_pos);
-                        self.level.triggers.push(Trigger {
-                            pos: self.cursor_pos,
-                            id: "exit1".into(),
-                        });
-                        self.event(UiEventType::Changed)
+            (EditorMode::Play, Action::AdoptTest) => {
+                self.mode = EditorMode::View;
+                self.adopt_test_state();
+            }
+            (EditorMode::Play, Action::SaveSolution) => {
+                self.mode = EditorMode::View;
+                self.save_test_solution();
+            }
+            (EditorMode::WriteText | EditorMode::Paint | EditorMode::SetMarkers | EditorMode::Play, Action::Cancel) => {
+                self.mode = EditorMode::View;
+            }
+            _ => return None,
+        }
+        self.event(UiEventType::Changed)
+    }
+}
+
+impl UiWidget for LevelEditor {
+    fn print(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        if self.need_refresh() {
+            match self.mode {
+                EditorMode::ErrorMessage => {}
+                EditorMode::Play => {
+                    self.test_runer.print(ui);
+                }
+                _ => {
+                    queue!(ui.stdout, terminal::Clear(terminal::ClearType::All), style::ResetColor)?;
+                    self.print_level(ui)?;
+                    if let Some((_, menu)) = &mut self.stamp_menu {
+                        menu.mark_refresh(true);
+                        menu.print(ui)?;
                     }
-                    Event::Key(KeyEvent { code: KeyCode::Char('c'), modifiers: KeyModifiers::NONE }) => {
-                        self.level.triggers.retain(|trigger| trigger.pos != self.cursor_pos);
-                        self.level.triggers.push(Trigger {
-                            pos: self.cursor_pos,
-                            id: "exit2".into(),
-                        });
-                        self.event(UiEventType::Changed)
+                    if let Some((_, prompt)) = &mut self.prompt {
+                        prompt.mark_refresh(true);
+                        prompt.print(ui)?;
                     }
-                    Event::Key(KeyEvent { code: KeyCode::Char('v'), modifiers: KeyModifiers::NONE }) => {
-                        self.level.triggers.retain(|trigger| trigger.pos != self.cursor_pos);
-                        self.level.triggers.push(Trigger {
-                            pos: self.cursor_pos,
-                            id: "exit0".into(),
-                        });
-                        self.event(UiEventType::Changed)
+                    if let Some(help) = &mut self.help {
+                        help.mark_refresh(true);
+                        help.print(ui)?;
                     }
-                    _ => None
+                    ui.stdout.flush()?
                 }
             }
-            // already handled
-            EditorMode::ErrorMessage => None,
-            EditorMode::Play => None
-        };
-        None
+            self.need_refresh = false;
+        }
+        Ok(())
+    }
+
+    fn input(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
+        self.edit_continued = false;
+        let result = self.handle_input(e, ui);
+        // Any deliberate action which doesn't continue the current edit finishes it,
+        // mouse movement and other noise doesn't.
+        let ends_edit = matches!(e,
+            Event::Key(_) |
+            Event::Mouse(MouseEvent { kind: MouseEventKind::Down(_), .. }) |
+            Event::Mouse(MouseEvent { kind: MouseEventKind::Up(_), .. }));
+        if ends_edit && !self.edit_continued {
+            self.history.commit(&self.level);
+        }
+        result
     }
 
     fn update(&mut self) -> Option<UiEvent> {
+        if self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            self.autosave();
+        }
         if self.mode == EditorMode::Play {
             let r = self.test_runer.update();
             return self.handle_test_play(r);
@@ -839,12 +2060,28 @@ impl UiWidget for LevelEditor {
     }
 
     fn resize(&mut self, widget_size: &Rectangle) {
+        if let Some((_, prompt)) = &mut self.prompt {
+            prompt.resize(widget_size);
+        }
+        if let Some((_, menu)) = &mut self.stamp_menu {
+            menu.resize(widget_size);
+        }
         self.need_refresh = true;
     }
 
     fn get_id(&self) -> UiId {
         return self.id;
     }
+
+    fn quit_requested(&mut self, _ui: &mut UiContext) -> bool {
+        // Ctrl+C while already asking is treated as force quit.
+        if self.mode == EditorMode::ConfirmQuit || !self.has_unsaved_changes() {
+            return true;
+        }
+        self.mode = EditorMode::ConfirmQuit;
+        self.mark_refresh(true);
+        false
+    }
 }
 
 pub struct LevelRunner {
@@ -853,15 +2090,50 @@ pub struct LevelRunner {
     pub pos: V2,
     view_corner: V2,
     pub need_refresh: bool,
+    /// Directions of all the successful moves since level start.
+    history: Vec<V2>,
+    /// Player position before the first move in `history`.
+    start_pos: V2,
+    pushes: u32,
+    pub restarts: u32,
+    started: Instant,
+    /// Quick save file, without one the quick save is only kept in memory.
+    pub save_slot: Option<PathBuf>,
+    memory_slot: Option<RunnerState>,
+    status: String,
+    keymap: Keymap,
+    keymap_error: Option<String>,
+    pub help_contexts: &'static [KeyContext],
+    help: Option<TextView>,
     id: UiId,
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+struct RunnerState {
+    /// `stats::level_hash` of the start level, older saves don't have it.
+    #[serde(default)]
+    level_hash: Option<String>,
+    level: Level,
+    start_level: Level,
+    pos: V2,
+    view_corner: V2,
+    history: Vec<V2>,
+    #[serde(default)]
+    pushes: u32,
+    #[serde(default)]
+    elapsed_ms: u64,
+}
+
 fn is_base_color(c: CellColor) -> bool {
     return c == CellColor::Black || c == CellColor::White;
 }
 
 impl LevelRunner {
     pub fn new(ui: &mut UiContext) -> LevelRunner {
+        LevelRunner::with_keymap(ui, Keymap::load())
+    }
+
+    pub fn with_keymap(ui: &mut UiContext, (keymap, keymap_error): (Keymap, Option<String>)) -> LevelRunner {
         LevelRunner {
             id: ui.next_id(),
             level: Level::new(10, 10),
@@ -869,6 +2141,18 @@ impl LevelRunner {
             pos: V2::make(2, 2),
             view_corner: V2::make(0, 0),
             need_refresh: true,
+            history: vec![],
+            start_pos: V2::make(2, 2),
+            pushes: 0,
+            restarts: 0,
+            started: Instant::now(),
+            save_slot: None,
+            memory_slot: None,
+            status: String::new(),
+            keymap,
+            keymap_error,
+            help_contexts: keymap::GAME_KEYS,
+            help: None,
         }
     }
     pub fn new_with_level(ui: &mut UiContext, level: &Level) -> LevelRunner {
@@ -876,6 +2160,7 @@ impl LevelRunner {
         res.level = level.clone();
         res.backup_level = level.clone();
         res.pos = level.p0;
+        res.start_pos = level.p0;
 
         res
     }
@@ -891,12 +2176,120 @@ impl LevelRunner {
 
     pub fn start(&mut self) {
         self.pos = self.level.p0;
+        self.start_pos = self.pos;
         self.backup_level = self.level.clone();
+        self.history.clear();
+        self.pushes = 0;
+        self.restarts = 0;
+        self.started = Instant::now();
+        self.status = self.keymap_error.clone()
+            .unwrap_or_else(|| self.keymap.describe(KeyContext::Game, &[Action::Help]));
+    }
+
+    /// Moves the player to `pos` before any moves are made, restarting goes back to `p0`.
+    pub fn start_at(&mut self, pos: V2) {
+        self.pos = pos;
+        self.start_pos = pos;
+    }
+
+    pub fn showing_help(&self) -> bool {
+        self.help.is_some()
+    }
+
+    fn open_help(&mut self, ui: &mut UiContext) {
+        let lines = self.keymap.help_lines(self.help_contexts);
+        let mut help = TextView::new("Help", lines, ui);
+        let size = ui.buffer_size();
+        help.resize(&Rectangle { pos: V2::new(), size: V2::from(size) });
+        self.help = Some(help);
+    }
+
+    fn handle_help(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
+        if !matches!(e, Event::Key(_)) {
+            return None;
+        }
+        self.need_refresh = true;
+        let toggled = self.keymap.action(self.help_contexts, e) == Some(Action::Help);
+        let help = self.help.as_mut()?;
+        if toggled || matches!(help.input(e, ui), Some(UiEvent { e: UiEventType::Canceled, .. })) {
+            self.help = None;
+        }
+        self.event(UiEventType::Changed)
+    }
+
+    pub fn moves(&self) -> u32 {
+        self.history.len() as u32
+    }
+
+    pub fn pushes(&self) -> u32 {
+        self.pushes
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn quick_save(&mut self) {
+        let state = RunnerState {
+            level_hash: Some(stats::level_hash(&self.backup_level)),
+            level: self.level.clone(),
+            start_level: self.backup_level.clone(),
+            pos: self.pos,
+            view_corner: self.view_corner,
+            history: self.history.clone(),
+            pushes: self.pushes,
+            elapsed_ms: self.elapsed_ms(),
+        };
+        let result = match &self.save_slot {
+            Some(path) => storage::save_yaml(path, &state),
+            None => {
+                self.memory_slot = Some(state);
+                Ok(())
+            }
+        };
+        self.status = match result {
+            Ok(_) => "Quick saved".into(),
+            Err(e) => format!("Quick save failed: {}", e),
+        };
+    }
+
+    fn quick_load(&mut self) {
+        let loaded = match &self.save_slot {
+            Some(path) if path.is_file() => storage::load_yaml::<RunnerState>(path).map(Some),
+            Some(_) => Ok(None),
+            None => Ok(self.memory_slot.clone()),
+        };
+        match loaded {
+            Ok(None) => {
+                self.status = "No quick save for this level".into();
+            }
+            Ok(Some(state)) if state.level_hash.clone()
+                .unwrap_or_else(|| stats::level_hash(&state.start_level)) != stats::level_hash(&self.backup_level) => {
+                self.status = "Quick save is from a different version of this level".into();
+            }
+            Ok(Some(state)) => {
+                self.level = state.level;
+                self.backup_level = state.start_level;
+                self.pos = state.pos;
+                self.view_corner = state.view_corner;
+                self.history = state.history;
+                self.start_pos = self.backup_level.p0;
+                self.pushes = state.pushes;
+                self.started = Instant::now().checked_sub(Duration::from_millis(state.elapsed_ms))
+                    .unwrap_or_else(Instant::now);
+                self.status = "Quick loaded".into();
+            }
+            Err(e) => {
+                self.status = format!("Quick load failed: {}", e);
+            }
+        }
     }
 
     pub fn restart(&mut self) {
+        let restarts = self.restarts + 1;
         self.level = self.backup_level.clone();
         self.start();
+        self.restarts = restarts;
     }
 
     fn print_level(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
@@ -936,6 +2329,10 @@ impl LevelRunner {
                         .with(get_color(cell.foreground))
                         .on(get_color(cell.background))))?;
         }
+        if !self.status.is_empty() {
+            queue!(ui.stdout, cursor::MoveTo(0, size.1 - 1), style::ResetColor,
+                style::Print(&self.status))?;
+        }
         /*self.print_rect(ui, Rectangle { pos: V2::make(-1, -1), size: V2::make(self.level.width + 2, 1) }, ' ');
         self.print_rect(ui, Rectangle { pos: V2::make(-1, self.level.height), size: V2::make(self.level.width + 2, 1) }, ' ');
         self.print_rect(ui, Rectangle { pos: V2::make(-1, -1), size: V2::make(1, self.level.height + 2) }, ' ');
@@ -999,6 +2396,7 @@ impl LevelRunner {
                         self.level.set(target, target2);
                         self.level.set(target + dir, next2);
                         self.pos = target;
+                        self.pushes += 1;
                         return;
                     }
                     if next_cell.background != target_cell.background && next_cell.letter == target_cell.letter {
@@ -1010,6 +2408,7 @@ impl LevelRunner {
                         self.level.set(target, target2);
                         self.level.set(target + dir, next2);
                         self.pos = target;
+                        self.pushes += 1;
                         return;
                     }
                 }
@@ -1033,7 +2432,12 @@ impl LevelRunner {
     }
 
     fn move_with_ui(&mut self, dir: V2, ui: &mut UiContext) {
+        let old_pos = self.pos;
         self.walk(dir);
+        if self.pos != old_pos {
+            self.history.push(dir);
+            self.status.clear();
+        }
         self.keep_cursor_in_view();
         self.mark_refresh(true);
     }
@@ -1048,6 +2452,10 @@ impl UiWidget for LevelRunner {
         if self.need_refresh {
             queue!(ui.stdout,Clear(ClearType::All));
             self.print_level(ui)?;
+            if let Some(help) = &mut self.help {
+                help.mark_refresh(true);
+                help.print(ui)?;
+            }
             ui.stdout.flush();
             self.need_refresh = false;
         }
@@ -1055,31 +2463,45 @@ impl UiWidget for LevelRunner {
     }
 
     fn input(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
-        match e {
-            Event::Key(KeyEvent { code: KeyCode::Char('w'), modifiers: KeyModifiers::NONE }) |
-            Event::Key(KeyEvent { code: KeyCode::Up, modifiers: KeyModifiers::NONE }) => {
+        if self.help.is_some() {
+            return self.handle_help(e, ui);
+        }
+        match self.keymap.action(keymap::GAME_KEYS, e) {
+            Some(Action::Help) => {
+                self.open_help(ui);
+                self.mark_refresh(true);
+                self.event(UiEventType::Changed)
+            }
+            Some(Action::MoveUp) => {
                 self.move_with_ui(V2::make(0, -1), ui);
                 self.event(UiEventType::Changed)
             }
-            Event::Key(KeyEvent { code: KeyCode::Char('s'), modifiers: KeyModifiers::NONE }) |
-            Event::Key(KeyEvent { code: KeyCode::Down, modifiers: KeyModifiers::NONE }) => {
+            Some(Action::MoveDown) => {
                 self.move_with_ui(V2::make(0, 1), ui);
                 self.event(UiEventType::Changed)
             }
-            Event::Key(KeyEvent { code: KeyCode::Char('a'), modifiers: KeyModifiers::NONE }) |
-            Event::Key(KeyEvent { code: KeyCode::Left, modifiers: KeyModifiers::NONE }) => {
+            Some(Action::MoveLeft) => {
                 self.move_with_ui(V2::make(-1, 0), ui);
                 self.event(UiEventType::Changed)
             }
-            Event::Key(KeyEvent { code: KeyCode::Char('d'), modifiers: KeyModifiers::NONE }) |
-            Event::Key(KeyEvent { code: KeyCode::Right, modifiers: KeyModifiers::NONE }) => {
+            Some(Action::MoveRight) => {
                 self.move_with_ui(V2::make(1, 0), ui);
                 self.event(UiEventType::Changed)
             }
-            Event::Key(KeyEvent { code: KeyCode::Char('r'), modifiers: KeyModifiers::NONE }) => {
+            Some(Action::Restart) => {
                 self.restart();
                 self.event(UiEventType::Changed)
             }
+            Some(Action::QuickSave) => {
+                self.quick_save();
+                self.mark_refresh(true);
+                self.event(UiEventType::Changed)
+            }
+            Some(Action::QuickLoad) => {
+                self.quick_load();
+                self.mark_refresh(true);
+                self.event(UiEventType::Changed)
+            }
             _ => None
         }
     }
@@ -1125,6 +2547,39 @@ impl UiWidget for LevelRunner {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum TitleEntry {
+    Continue,
+    NewGame,
+    LevelSelect,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum PauseEntry {
+    Resume,
+    Restart,
+    Skip,
+    LevelSelect,
+    Options,
+    Quit,
+}
+
+const PAUSE_ENTRIES: [(PauseEntry, &str); 6] = [
+    (PauseEntry::Resume, "Resume"),
+    (PauseEntry::Restart, "Restart level"),
+    (PauseEntry::Skip, "Skip level"),
+    (PauseEntry::LevelSelect, "Level select"),
+    (PauseEntry::Options, "Options"),
+    (PauseEntry::Quit, "Quit"),
+];
+
+enum RunnerMenu {
+    Title(Vec<TitleEntry>),
+    LevelSelect { from_title: bool },
+    Pause,
+    Options,
+}
+
 pub struct MultiLevelRunner {
     id: UiId,
     levels: LevelList,
@@ -1133,10 +2588,19 @@ pub struct MultiLevelRunner {
     can_exit: i32,
     need_refresh: bool,
     message: String,
+    progress: Progress,
+    stats: Stats,
+    level_hash: String,
+    completion: Option<Vec<String>>,
+    menu: Option<(RunnerMenu, Menu)>,
+    window: Rectangle,
+    settings: Settings,
+    quit: bool,
 }
 
 impl MultiLevelRunner {
     pub fn new(ui: &mut UiContext, levels: LevelList) -> MultiLevelRunner {
+        let progress = Progress::load();
         let mut res = MultiLevelRunner {
             id: ui.next_id(),
             levels,
@@ -1145,26 +2609,266 @@ impl MultiLevelRunner {
             can_exit: 0,
             need_refresh: true,
             message: String::new(),
+            progress,
+            stats: Stats::load(),
+            level_hash: String::new(),
+            completion: None,
+            menu: None,
+            window: ui::DEFAULT_WINDOW_SIZE,
+            settings: Settings::load(),
+            quit: false,
         };
-
+        res.open_title_menu(ui);
         res
     }
 
+    fn show_menu(&mut self, kind: RunnerMenu, mut menu: Menu) {
+        menu.resize(&self.window);
+        self.menu = Some((kind, menu));
+    }
+
+    fn open_title_menu(&mut self, ui: &mut UiContext) {
+        let mut entries = vec![];
+        let mut names = vec![];
+        if let Some(index) = self.progress.continue_index(&self.levels) {
+            entries.push(TitleEntry::Continue);
+            names.push(format!("Continue (level {}/{})", index + 1, self.levels.files.len()));
+        }
+        entries.push(TitleEntry::NewGame);
+        names.push("New game".into());
+        entries.push(TitleEntry::LevelSelect);
+        names.push("Level select".into());
+        let mut menu = Menu::new(names, false, ui);
+        if self.levels.files.is_empty() {
+            menu.set_enabled(entries.len() - 1, false);
+        }
+        self.show_menu(RunnerMenu::Title(entries), menu);
+    }
+
+    fn open_level_select(&mut self, ui: &mut UiContext, from_title: bool) {
+        if self.levels.files.is_empty() {
+            self.message = "No levels".into();
+            return;
+        }
+        let unlocked = self.progress.furthest_index(&self.levels).unwrap_or(0);
+        let entries = self.levels.files.iter().enumerate().map(|(i, name)| {
+            let status = if i > unlocked {
+                "locked".to_owned()
+            } else if let Some(record) = self.progress.get(name) {
+                match record.best_moves {
+                    Some(moves) => format!("completed ({}), best {} moves", record.exit, moves),
+                    None => format!("completed ({})", record.exit),
+                }
+            } else {
+                String::new()
+            };
+            format!("{:<16} {}", name, status)
+        }).collect();
+        let mut menu = Menu::new(entries, true, ui);
+        menu.set_title("Select level");
+        for i in (unlocked + 1)..self.levels.files.len() {
+            menu.set_enabled(i, false);
+        }
+        menu.select(min(self.current_level, unlocked));
+        self.show_menu(RunnerMenu::LevelSelect { from_title }, menu);
+    }
+
+    fn can_skip(&self) -> bool {
+        let completed = self.levels.files.get(self.current_level)
+            .is_some_and(|name| self.progress.is_completed(name));
+        completed || self.settings.allow_skip
+    }
+
+    fn open_pause_menu(&mut self, ui: &mut UiContext) {
+        let entries = PAUSE_ENTRIES.iter().map(|(_, name)| name.to_string()).collect();
+        let mut menu = Menu::new(entries, true, ui);
+        let name = self.levels.files.get(self.current_level).map_or("", |name| name.as_str());
+        menu.set_title(&format!("Paused - {}", name));
+        menu.set_overlay(true);
+        if !self.can_skip() {
+            menu.set_enabled(2, false);
+        }
+        self.show_menu(RunnerMenu::Pause, menu);
+    }
+
+    fn open_options_menu(&mut self, ui: &mut UiContext) {
+        let on_off = |value: bool| if value { "on" } else { "off" };
+        let entries = vec![
+            format!("Allow skipping unsolved levels: {}", on_off(self.settings.allow_skip)),
+            "Back".into(),
+        ];
+        let mut menu = Menu::new(entries, true, ui);
+        menu.set_title("Options");
+        menu.set_overlay(true);
+        self.show_menu(RunnerMenu::Options, menu);
+    }
+
+    fn resume(&mut self) {
+        self.menu = None;
+        self.level_runner.mark_refresh(true);
+    }
+
+    fn handle_pause_menu(&mut self, entry: PauseEntry, ui: &mut UiContext) {
+        match entry {
+            PauseEntry::Resume => self.resume(),
+            PauseEntry::Restart => {
+                self.level_runner.restart();
+                self.current_stats().restarts += 1;
+                let _ = self.stats.save();
+                self.resume();
+            }
+            PauseEntry::Skip => {
+                self.menu = None;
+                self.current_level += 1;
+                self.start_next_level();
+            }
+            PauseEntry::LevelSelect => self.open_level_select(ui, false),
+            PauseEntry::Options => self.open_options_menu(ui),
+            PauseEntry::Quit => {
+                self.menu = None;
+                self.quit = true;
+                self.message = "Progress saved, choose Continue next time to resume.".into();
+            }
+        }
+    }
+
+    fn handle_menu_events(&mut self, ev: Option<UiEvent>, ui: &mut UiContext) -> Option<UiEvent> {
+        let kind = match &self.menu {
+            Some((kind, _)) => kind,
+            None => return None,
+        };
+        match (kind, ev) {
+            (RunnerMenu::Title(entries), Some(UiEvent { e: UiEventType::Result(res), .. })) => {
+                let selected = res.downcast_ref::<usize>().and_then(|i| entries.get(*i)).copied();
+                self.menu = None;
+                match selected {
+                    Some(TitleEntry::Continue) => {
+                        self.current_level = self.progress.continue_index(&self.levels).unwrap_or(0);
+                        self.start_next_level();
+                    }
+                    Some(TitleEntry::LevelSelect) => {
+                        self.open_level_select(ui, true);
+                    }
+                    Some(TitleEntry::NewGame) | None => {
+                        self.current_level = 0;
+                        self.start_next_level();
+                    }
+                }
+                self.event(UiEventType::Changed)
+            }
+            (RunnerMenu::LevelSelect { .. }, Some(UiEvent { e: UiEventType::Result(res), .. })) => {
+                self.menu = None;
+                if let Some(index) = res.downcast_ref::<usize>() {
+                    self.current_level = *index;
+                }
+                self.start_next_level();
+                self.event(UiEventType::Changed)
+            }
+            (RunnerMenu::LevelSelect { from_title }, Some(UiEvent { e: UiEventType::Canceled, .. })) => {
+                if *from_title {
+                    self.open_title_menu(ui);
+                } else {
+                    self.open_pause_menu(ui);
+                }
+                self.event(UiEventType::Changed)
+            }
+            (RunnerMenu::Pause, Some(UiEvent { e: UiEventType::Result(res), .. })) => {
+                if let Some((entry, _)) = res.downcast_ref::<usize>().and_then(|i| PAUSE_ENTRIES.get(*i)) {
+                    self.handle_pause_menu(*entry, ui);
+                }
+                self.event(UiEventType::Changed)
+            }
+            (RunnerMenu::Pause, Some(UiEvent { e: UiEventType::Canceled, .. })) => {
+                self.resume();
+                self.event(UiEventType::Changed)
+            }
+            (RunnerMenu::Options, Some(UiEvent { e: UiEventType::Result(res), .. })) => {
+                if res.downcast_ref::<usize>() == Some(&0) {
+                    self.settings.allow_skip = !self.settings.allow_skip;
+                    let _ = self.settings.save();
+                    self.open_options_menu(ui);
+                } else {
+                    self.open_pause_menu(ui);
+                }
+                self.event(UiEventType::Changed)
+            }
+            (RunnerMenu::Options, Some(UiEvent { e: UiEventType::Canceled, .. })) => {
+                self.open_pause_menu(ui);
+                self.event(UiEventType::Changed)
+            }
+            (_, None) => None,
+            _ => self.event(UiEventType::Changed),
+        }
+    }
+
+    fn complete_level(&mut self, exit: &str) {
+        let name = self.levels.files.get(self.current_level).cloned().unwrap_or_default();
+        let moves = self.level_runner.moves();
+        let pushes = self.level_runner.pushes();
+        let time = self.level_runner.elapsed_ms();
+        self.progress.record_completion(&name, exit, moves);
+        let _ = self.progress.save();
+
+        let stats = self.current_stats();
+        let previous = stats.clone();
+        stats.record_completion(exit, time, moves, pushes);
+        let best = |value: Option<u32>| value.map_or(String::new(), |v| format!("  best: {}", v));
+        let best_time = previous.best_time_ms.map_or(String::new(),
+                                                     |t| format!("  best: {}", stats::format_time(t)));
+        let summary = vec![
+            format!("Level complete: {} ({})", name, exit),
+            String::new(),
+            format!("Moves:  {}{}", moves, best(previous.fewest_moves)),
+            format!("Pushes: {}{}", pushes, best(previous.fewest_pushes)),
+            format!("Time:   {}{}", stats::format_time(time), best_time),
+            format!("Attempts: {}  Restarts: {}  Completions: {}",
+                    stats.attempts, stats.restarts, stats.completions),
+            format!("Exits found: {}", stats.exits.join(", ")),
+            String::new(),
+            "Press Enter to continue".into(),
+        ];
+        let _ = self.stats.save();
+        self.completion = Some(summary);
+        self.need_refresh = true;
+    }
+
+    fn current_stats(&mut self) -> &mut LevelStats {
+        let name = self.levels.files.get(self.current_level).map_or("", |name| name.as_str());
+        self.stats.entry(&self.level_hash, name)
+    }
+
+    fn finish_completion(&mut self) {
+        self.completion = None;
+        self.current_level += 1;
+        self.start_next_level();
+    }
+
+    fn print_completion(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        if let Some(lines) = &self.completion {
+            queue!(ui.stdout, style::ResetColor, Clear(ClearType::All), cursor::Hide)?;
+            for (i, line) in lines.iter().enumerate() {
+                queue!(ui.stdout, cursor::MoveTo(2, 1 + i as u16), style::Print(line))?;
+            }
+            ui.stdout.flush()?;
+        }
+        self.need_refresh = false;
+        Ok(())
+    }
+
     pub fn running(&self) -> bool {
-        return self.current_level < self.levels.files.len();
+        !self.quit && self.current_level < self.levels.files.len()
     }
 
     fn handle_level_runner_events(&mut self, ev: &Option<UiEvent>) -> Option<UiEvent> {
         match ev {
             Some(UiEvent { id, e: UiEventType::Ok }) |
             Some(UiEvent { id, e: UiEventType::Canceled }) if *id == self.level_runner.get_id() => {
-                self.current_level += 1;
-                self.start_next_level();
+                self.complete_level("exit0");
                 self.event(UiEventType::Changed)
             }
             Some(UiEvent { id, e: UiEventType::Result(res) }) if *id == self.level_runner.get_id() => {
-                self.current_level += 1;
-                self.start_next_level();
+                let exit = res.downcast_ref::<String>().cloned().unwrap_or_default();
+                self.complete_level(&exit);
                 //TODO: good path bad path counting
                 self.event(UiEventType::Changed)
             }
@@ -1191,11 +2895,20 @@ impl MultiLevelRunner {
     }
 
     pub fn start_next_level(&mut self) {
-        if let Some(path) = self.levels.files.get(self.current_level) {
-            let path2 = path.clone();
+        if let Some(path) = self.levels.path(self.current_level) {
+            let path2 = path.to_string_lossy().into_owned();
             if let Ok(level) = self.load_level(&path2) {
                 self.level_runner.level = level;
                 self.level_runner.start();
+                self.level_runner.save_slot = self.levels.files.get(self.current_level)
+                    .and_then(|name| storage::data_file(&storage::slot_file_name("quicksave", name)));
+                self.level_runner.mark_refresh(true);
+                self.progress.reach(&self.levels, self.current_level);
+                // Progress is best effort, failing to write it shouldn't interrupt the game.
+                let _ = self.progress.save();
+                self.level_hash = stats::level_hash(&self.level_runner.level);
+                self.current_stats().attempts += 1;
+                let _ = self.stats.save();
             } else {
                 if self.message.is_empty() {
                     self.message = "Failed to load level".into();
@@ -1210,7 +2923,18 @@ impl MultiLevelRunner {
 
 impl UiWidget for MultiLevelRunner {
     fn print(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
-        if self.running() {
+        if let Some((_, menu)) = &mut self.menu {
+            if menu.is_overlay() && menu.need_refresh() {
+                // keep the level visible behind the menu
+                self.level_runner.mark_refresh(true);
+                self.level_runner.print(ui)?;
+            }
+            menu.print(ui)?;
+        } else if self.completion.is_some() {
+            if self.need_refresh {
+                self.print_completion(ui)?;
+            }
+        } else if self.running() {
             self.level_runner.print(ui)?;
         } else {
             if !self.message.is_empty() {
@@ -1224,8 +2948,29 @@ impl UiWidget for MultiLevelRunner {
     }
 
     fn input(&mut self, e: &Event, ui: &mut UiContext) -> Option<UiEvent> {
-        if self.running() {
+        if let Some((_, menu)) = &mut self.menu {
+            let ui_event = menu.input(e, ui);
+            self.handle_menu_events(ui_event, ui)
+        } else if self.completion.is_some() {
+            match e {
+                Event::Key(KeyEvent { code: KeyCode::Enter, modifiers: KeyModifiers::NONE }) |
+                Event::Key(KeyEvent { code: KeyCode::Char(' '), modifiers: KeyModifiers::NONE }) => {
+                    self.finish_completion();
+                    self.event(UiEventType::Changed)
+                }
+                _ => None
+            }
+        } else if self.running() {
+            if self.level_runner.keymap.action(keymap::GAME_KEYS, e) == Some(Action::Pause) && !self.level_runner.showing_help() {
+                self.open_pause_menu(ui);
+                return self.event(UiEventType::Changed);
+            }
+            let restarts = self.level_runner.restarts;
             let ui_event = self.level_runner.input(e, ui);
+            if self.level_runner.restarts > restarts {
+                self.current_stats().restarts += 1;
+                let _ = self.stats.save();
+            }
             self.handle_level_runner_events(&ui_event)
         } else {
             match e {
@@ -1255,6 +3000,10 @@ impl UiWidget for MultiLevelRunner {
     }
 
     fn resize(&mut self, widget_size: &Rectangle) {
+        self.window = *widget_size;
+        if let Some((_, menu)) = &mut self.menu {
+            menu.resize(widget_size);
+        }
         if self.running() {
             self.level_runner.resize(widget_size);
         }
@@ -1263,6 +3012,9 @@ impl UiWidget for MultiLevelRunner {
     fn get_id(&self) -> UiId { self.id }
 
     fn update(&mut self) -> Option<UiEvent> {
+        if self.menu.is_some() || self.completion.is_some() {
+            return None;
+        }
         if self.running() {
             let ui_ev = self.level_runner.update();
             return self.handle_level_runner_events(&ui_ev);
@@ -1275,4 +3027,507 @@ impl UiWidget for MultiLevelRunner {
         }
         None
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum DiffView {
+    New,
+    Old,
+    SideBySide,
+}
+
+impl DiffView {
+    fn next(&self) -> DiffView {
+        match self {
+            DiffView::New => DiffView::Old,
+            DiffView::Old => DiffView::SideBySide,
+            DiffView::SideBySide => DiffView::New,
+        }
+    }
+}
+
+/// Two versions of a level drawn like in the editor with the changed cells highlighted.
+pub struct DiffViewer {
+    id: UiId,
+    names: (String, String),
+    old: Level,
+    new: Level,
+    diff: LevelDiff,
+    changed: HashSet<V2>,
+    view: DiffView,
+    layer_view: LayerView,
+    view_corner: V2,
+    region: Option<usize>,
+    keymap: Keymap,
+    keymap_error: Option<String>,
+    size: V2,
+    need_refresh: bool,
+}
+
+
+impl DiffViewer {
+    pub fn new(ui: &mut UiContext, names: (String, String), old: Level, new: Level) -> DiffViewer {
+        let diff = LevelDiff::compare(&old, &new);
+        let (keymap, keymap_error) = Keymap::load();
+        let mut result = DiffViewer {
+            id: ui.next_id(),
+            names,
+            changed: diff.cells.iter().copied().collect(),
+            old,
+            new,
+            diff,
+            view: DiffView::New,
+            layer_view: LayerView::All,
+            view_corner: V2::make(-1, -1),
+            region: None,
+            keymap,
+            keymap_error,
+            size: V2::from(buffer_size()),
+            need_refresh: true,
+        };
+        result.next_region(1);
+        result
+    }
+
+    /// Screen area of a single level, the last row is used by the status bar.
+    fn pane_size(&self) -> V2 {
+        let height = max(1, self.size.y - 1);
+        match self.view {
+            DiffView::SideBySide => V2::make(max(1, (self.size.x - 1) / 2), height),
+            _ => V2::make(self.size.x, height),
+        }
+    }
+
+    fn next_region(&mut self, step: i32) {
+        let count = self.diff.regions.len() as i32;
+        if count == 0 {
+            return;
+        }
+        let index = match self.region {
+            Some(index) => (index as i32 + step).rem_euclid(count),
+            None if step > 0 => 0,
+            None => count - 1,
+        };
+        self.region = Some(index as usize);
+        let region = self.diff.regions[index as usize].bounds;
+        let center = V2::make(region.left() + region.width() / 2, region.top() + region.height() / 2);
+        let pane = self.pane_size();
+        self.view_corner = center - V2::make(pane.x / 2, pane.y / 2);
+    }
+
+    fn print_pane(&self, ui: &mut UiContext, level: &Level, screen_pos: V2) -> std::io::Result<()> {
+        let pane = self.pane_size();
+        for y in 0..pane.y {
+            ui.goto(screen_pos + V2::make(0, y))?;
+            for x in 0..pane.x {
+                let pos = self.view_corner + V2::make(x, y);
+                let (c, foreground, background) = if !level.contains(pos) {
+                    (' ', Color::Reset, Color::Reset)
+                } else if pos == level.p0 {
+                    ('$', Color::DarkGreen, get_color(level[pos].background))
+                } else if level.triggers.iter().any(|trigger| trigger.pos == pos) {
+                    ('?', Color::Red, get_color(level[pos].background))
+                } else {
+                    cell_style(&level[pos], self.layer_view)
+                };
+                let background = if self.changed.contains(&pos) { Color::DarkRed } else { background };
+                queue!(ui.stdout, style::PrintStyledContent(style::style(c)
+                    .with(foreground)
+                    .on(background)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_status_bar(&self, ui: &mut UiContext) -> std::io::Result<()> {
+        let shown = match self.view {
+            DiffView::New => format!("showing {}", self.names.1),
+            DiffView::Old => format!("showing {}", self.names.0),
+            DiffView::SideBySide => format!("left {} right {}", self.names.0, self.names.1),
+        };
+        let changes = match self.region {
+            Some(index) => format!("area {}/{}", index + 1, self.diff.regions.len()),
+            None if self.diff.is_empty() => "no changes".into(),
+            None => "no changed cells".into(),
+        };
+        let keys = |context, action| self.keymap.keys_text(context, action);
+        let text = match &self.keymap_error {
+            Some(error) => format!("{} | {}", shown, error),
+            None => format!("{} | {} | layer: {:?} | {}{}{}{}[{}/{}/{}/{}] scroll",
+                            shown, changes, self.layer_view,
+                            self.keymap.describe(KeyContext::Diff, &[Action::ToggleView]),
+                            self.keymap.describe(KeyContext::View, &[Action::FindNext]),
+                            self.keymap.describe(KeyContext::Diff, &[Action::PreviousChange, Action::Quit]),
+                            self.keymap.describe(KeyContext::Editor, &[Action::CycleLayer]),
+                            keys(KeyContext::Pan, Action::PanUp), keys(KeyContext::Pan, Action::PanLeft),
+                            keys(KeyContext::Pan, Action::PanDown), keys(KeyContext::Pan, Action::PanRight)),
+        };
+        // the last screen row must not wrap, that would scroll the whole screen
+        let text: String = text.chars().take(max(0, self.size.x - 1) as usize).collect();
+        ui.goto(V2::make(0, self.size.y - 1))?;
+        queue!(ui.stdout, style::ResetColor, style::Print(text), Clear(ClearType::UntilNewLine))?;
+        Ok(())
+    }
+}
+
+impl UiWidget for DiffViewer {
+    fn print(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        if !self.need_refresh {
+            return Ok(());
+        }
+        queue!(ui.stdout, cursor::Hide, Clear(ClearType::All))?;
+        match self.view {
+            DiffView::New => self.print_pane(ui, &self.new, V2::new())?,
+            DiffView::Old => self.print_pane(ui, &self.old, V2::new())?,
+            DiffView::SideBySide => {
+                let pane = self.pane_size();
+                self.print_pane(ui, &self.old, V2::new())?;
+                for y in 0..pane.y {
+                    ui.goto(V2::make(pane.x, y))?;
+                    queue!(ui.stdout, style::ResetColor, style::Print('|'))?;
+                }
+                self.print_pane(ui, &self.new, V2::make(pane.x + 1, 0))?;
+            }
+        }
+        self.print_status_bar(ui)?;
+        ui.stdout.flush()?;
+        self.need_refresh = false;
+        Ok(())
+    }
+
+    fn input(&mut self, e: &Event, _ui: &mut UiContext) -> Option<UiEvent> {
+        self.keymap_error = None;
+        let step = match self.keymap.action(keymap::DIFF_KEYS, e) {
+            Some(Action::Cancel | Action::Quit) => return self.event(UiEventType::Ok),
+            Some(Action::FindNext) => {
+                self.next_region(1);
+                None
+            }
+            Some(Action::PreviousChange) => {
+                self.next_region(-1);
+                None
+            }
+            Some(Action::ToggleView) => {
+                self.view = self.view.next();
+                None
+            }
+            Some(Action::CycleLayer) => {
+                self.layer_view = self.layer_view.next();
+                None
+            }
+            Some(Action::PanUp | Action::CursorUp) => Some(V2::make(0, -1)),
+            Some(Action::PanDown | Action::CursorDown) => Some(V2::make(0, 1)),
+            Some(Action::PanLeft | Action::CursorLeft) => Some(V2::make(-1, 0)),
+            Some(Action::PanRight | Action::CursorRight) => Some(V2::make(1, 0)),
+            _ => return None,
+        };
+        if let Some(step) = step {
+            self.view_corner = self.view_corner + step;
+        }
+        self.need_refresh = true;
+        self.event(UiEventType::Changed)
+    }
+
+    fn child_widgets(&self) -> Vec<&dyn UiWidget> {
+        vec![]
+    }
+
+    fn child_widgets_mut(&mut self) -> Vec<&mut dyn UiWidget> {
+        vec![]
+    }
+
+    fn mark_refresh(&mut self, value: bool) {
+        self.need_refresh = value
+    }
+
+    fn need_refresh(&self) -> bool {
+        self.need_refresh
+    }
+
+    fn resize(&mut self, widget_size: &Rectangle) {
+        self.size = widget_size.size;
+        self.need_refresh = true;
+    }
+
+    fn get_id(&self) -> UiId {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(width: i32, height: i32) -> LevelEditor {
+        let mut stdout = std::io::stdout();
+        let mut ui = UiContext::create(&mut stdout).unwrap();
+        // the default keys, not the ones in the user's config
+        let mut editor = LevelEditor::with_keymap(&mut ui, (Keymap::default(), None));
+        editor.level = Level::new(width, height);
+        editor
+    }
+
+    fn row_text(level: &Level, y: i32) -> String {
+        (0..level.width).map(|x| match level[V2::make(x, y)].letter {
+            '\0' => '.',
+            c => c,
+        }).collect()
+    }
+
+    fn start_text(editor: &mut LevelEditor, pos: V2) {
+        editor.mode = EditorMode::WriteText;
+        editor.cursor_pos = pos;
+        editor.wrap_pos = pos;
+    }
+
+    #[test]
+    fn text_wraps_at_margin() {
+        let mut editor = editor(10, 4);
+        editor.text_margin = Some(4);
+        start_text(&mut editor, V2::make(1, 0));
+        editor.begin_edit(EditGroup::Text);
+        for c in "abcdef".chars() {
+            editor.write_letter(c);
+        }
+        assert_eq!(row_text(&editor.level, 0), ".abcd.....");
+        assert_eq!(row_text(&editor.level, 1), ".ef.......");
+        assert_eq!(editor.cursor_pos, V2::make(3, 1));
+    }
+
+    #[test]
+    fn insert_mode_shifts_row() {
+        let mut editor = editor(8, 2);
+        start_text(&mut editor, V2::make(0, 0));
+        editor.insert_text("abcd");
+        editor.text_insert = true;
+        editor.cursor_pos = V2::make(1, 0);
+        editor.begin_edit(EditGroup::Text);
+        editor.write_letter('x');
+        assert_eq!(row_text(&editor.level, 0), "axbcd...");
+        editor.delete_letter(V2::make(0, 0), true);
+        assert_eq!(row_text(&editor.level, 0), "xbcd....");
+        editor.undo();
+        assert_eq!(row_text(&editor.level, 0), "abcd....");
+    }
+
+    #[test]
+    fn backspace_stops_at_margin() {
+        let mut editor = editor(8, 2);
+        start_text(&mut editor, V2::make(2, 0));
+        editor.insert_text("ab");
+        let mut stdout = std::io::stdout();
+        let mut ui = UiContext::create(&mut stdout).unwrap();
+        for _ in 0..4 {
+            editor.handle_action(Action::Backspace, &mut ui);
+        }
+        assert_eq!(editor.cursor_pos, V2::make(2, 0));
+        assert_eq!(row_text(&editor.level, 0), "........");
+        start_text(&mut editor, V2::make(0, 1));
+        editor.handle_action(Action::Backspace, &mut ui);
+        assert_eq!(editor.cursor_pos, V2::make(0, 1));
+    }
+
+    #[test]
+    fn paste_clipboard_as_text() {
+        let mut editor = editor(10, 4);
+        start_text(&mut editor, V2::make(0, 0));
+        editor.insert_text("ab\n\ncd");
+        assert_eq!(row_text(&editor.level, 2), "cd........");
+        editor.undo();
+        start_text(&mut editor, V2::make(0, 0));
+        editor.insert_text("ab\ncd");
+        let stamp = Stamp::copy_from(&editor.level, Rectangle { pos: V2::make(0, 0), size: V2::make(2, 2) });
+        editor.clipboard = Some(stamp);
+        editor.text_margin = Some(6);
+        start_text(&mut editor, V2::make(5, 1));
+        let mut stdout = std::io::stdout();
+        let mut ui = UiContext::create(&mut stdout).unwrap();
+        editor.handle_action(Action::Paste, &mut ui);
+        assert_eq!(row_text(&editor.level, 1), "cd...ab...");
+        assert_eq!(row_text(&editor.level, 2), ".....cd...");
+        // the whole block is one undo step
+        editor.undo();
+        assert_eq!(row_text(&editor.level, 2), "..........");
+        assert_eq!(row_text(&editor.level, 1), "cd........");
+    }
+
+    #[test]
+    fn symmetry_only_mirrors_drawing() {
+        let mut editor = editor(6, 2);
+        editor.mode = EditorMode::Paint;
+        editor.symmetry = Some(Symmetry::Vertical);
+        editor.level.set(V2::make(0, 0), Cell { letter: 'a', ..Cell::make_empty() });
+        editor.replace(CellPattern::parse("a").unwrap(), CellPattern::parse("b").unwrap());
+        assert_eq!(row_text(&editor.level, 0), "b.....");
+        start_text(&mut editor, V2::make(1, 1));
+        editor.begin_edit(EditGroup::Text);
+        editor.write_letter('c');
+        assert_eq!(row_text(&editor.level, 1), ".c..c.");
+    }
+
+    #[test]
+    fn shape_with_chosen_letter() {
+        let mut editor = editor(6, 2);
+        editor.mode = EditorMode::Paint;
+        editor.apply_prompt(EditorPrompt::ShapeLetter, "xy");
+        assert_eq!(editor.shape_letter, None);
+        editor.apply_prompt(EditorPrompt::ShapeLetter, "x");
+        editor.cursor_pos = V2::make(1, 0);
+        editor.start_shape(Shape::Line);
+        editor.cursor_pos = V2::make(3, 0);
+        editor.finish_shape();
+        assert_eq!(row_text(&editor.level, 0), ".xxx..");
+        editor.apply_prompt(EditorPrompt::ShapeLetter, "");
+        assert_eq!(editor.shape_letter, None);
+    }
+
+    #[test]
+    fn paste_at_edge_clips_triggers() {
+        let mut editor = editor(6, 4);
+        editor.level.triggers.clear();
+        editor.level.set(V2::make(0, 0), Cell { letter: 'a', ..Cell::make_empty() });
+        editor.level.triggers.push(Trigger { pos: V2::make(0, 0), id: "exit0".into() });
+        editor.level.triggers.push(Trigger { pos: V2::make(1, 1), id: "exit1".into() });
+        let stamp = Stamp::copy_from(&editor.level, Rectangle { pos: V2::make(0, 0), size: V2::make(2, 2) });
+        editor.clipboard = Some(stamp);
+        editor.paste(V2::make(5, 3));
+        assert_eq!(editor.level[V2::make(5, 3)].letter, 'a');
+        let triggers: Vec<_> = editor.level.triggers.iter().map(|t| (t.pos, t.id.as_str())).collect();
+        assert_eq!(triggers, vec![(V2::make(0, 0), "exit0"), (V2::make(1, 1), "exit1"), (V2::make(5, 3), "exit0")]);
+    }
+
+    fn play_test(editor: &mut LevelEditor, start: V2, moves: &[V2]) {
+        editor.start_level_test(start);
+        for dir in moves {
+            editor.test_runer.walk(*dir);
+            editor.test_runer.history.push(*dir);
+        }
+        editor.mode = EditorMode::View;
+    }
+
+    #[test]
+    fn save_test_solution() {
+        let mut editor = editor(8, 6);
+        let right = V2::make(1, 0);
+        let start = editor.level.p0;
+        play_test(&mut editor, start + V2::make(0, 1), &[right]);
+        editor.save_test_solution();
+        assert_eq!(editor.status_message.as_deref(), Some("Solution must start at the level start, test with F8"));
+        assert!(editor.level.solution.is_empty());
+
+        play_test(&mut editor, start, &[right, right]);
+        editor.begin_edit(EditGroup::Single);
+        editor.set_cell(V2::make(0, 0), Cell { letter: 'x', ..Cell::make_empty() });
+        editor.save_test_solution();
+        assert_eq!(editor.status_message.as_deref(), Some("Level changed since the play-test"));
+        assert!(editor.level.solution.is_empty());
+
+        play_test(&mut editor, start, &[right, right]);
+        editor.save_test_solution();
+        assert_eq!(editor.level.solution, vec![right, right]);
+
+        play_test(&mut editor, start + V2::make(0, 1), &[right]);
+        editor.test_runer.restart();
+        editor.test_runer.walk(right);
+        editor.test_runer.history.push(right);
+        editor.save_test_solution();
+        assert_eq!(editor.level.solution, vec![right]);
+
+        play_test(&mut editor, start, &[]);
+        editor.test_runer.start_at(start + V2::make(0, 1));
+        editor.test_runer.history.push(right);
+        editor.save_test_solution();
+        assert_eq!(editor.status_message.as_deref(), Some("Solution must start at the level start, test with F8"));
+    }
+
+    #[test]
+    fn adopt_test_state() {
+        let mut editor = editor(8, 6);
+        editor.level.solution = vec![V2::make(0, 1)];
+        let start = editor.level.p0;
+        play_test(&mut editor, start, &[V2::make(1, 0)]);
+        editor.begin_edit(EditGroup::Single);
+        editor.set_cell(V2::make(0, 0), Cell { letter: 'x', ..Cell::make_empty() });
+        editor.adopt_test_state();
+        assert_eq!(editor.status_message.as_deref(), Some("Level changed since the play-test"));
+        assert_eq!(editor.level.p0, start);
+
+        play_test(&mut editor, start, &[V2::make(1, 0)]);
+        editor.adopt_test_state();
+        assert_eq!(editor.level.p0, start + V2::make(1, 0));
+        assert_eq!(editor.level[V2::make(0, 0)].letter, 'x');
+        assert!(editor.level.solution.is_empty());
+        editor.undo();
+        assert_eq!(editor.level.p0, start);
+        assert_eq!(editor.level.solution, vec![V2::make(0, 1)]);
+    }
+
+    fn runner_state() -> RunnerState {
+        let start_level = Level::new(6, 4);
+        let mut level = start_level.clone();
+        level.set(V2::make(1, 1), Cell { letter: 'a', ..Cell::make_empty() });
+        RunnerState {
+            level_hash: Some(stats::level_hash(&start_level)),
+            level,
+            start_level,
+            pos: V2::make(3, 2),
+            view_corner: V2::make(-1, 0),
+            history: vec![V2::make(1, 0), V2::make(0, -1)],
+            pushes: 2,
+            elapsed_ms: 1500,
+        }
+    }
+
+    #[test]
+    fn runner_state_round_trip() {
+        let text = serde_yaml::to_string(&runner_state()).unwrap();
+        let state: RunnerState = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(row_text(&state.level, 1), ".a....");
+        assert_eq!(row_text(&state.start_level, 1), "......");
+        assert_eq!(state.pos, V2::make(3, 2));
+        assert_eq!(state.view_corner, V2::make(-1, 0));
+        assert_eq!(state.history, vec![V2::make(1, 0), V2::make(0, -1)]);
+        assert_eq!(state.pushes, 2);
+        assert_eq!(state.elapsed_ms, 1500);
+    }
+
+    #[test]
+    fn runner_state_without_counters() {
+        let mut value = serde_yaml::to_value(runner_state()).unwrap();
+        let map = value.as_mapping_mut().unwrap();
+        map.remove(&"pushes".into());
+        map.remove(&"elapsed_ms".into());
+        map.remove(&"level_hash".into());
+        let state: RunnerState = serde_yaml::from_value(value).unwrap();
+        assert_eq!(state.level_hash, None);
+        assert_eq!(state.history.len(), 2);
+        assert_eq!(state.pushes, 0);
+        assert_eq!(state.elapsed_ms, 0);
+    }
+
+    #[test]
+    fn quick_load_checks_level() {
+        let mut stdout = std::io::stdout();
+        let mut ui = UiContext::create(&mut stdout).unwrap();
+        let mut runner = LevelRunner::with_keymap(&mut ui, (Keymap::default(), None));
+        runner.level = Level::new(8, 6);
+        runner.start();
+        runner.quick_load();
+        assert_eq!(runner.status, "No quick save for this level");
+        runner.walk(V2::make(1, 0));
+        runner.history.push(V2::make(1, 0));
+        runner.quick_save();
+        runner.restart();
+        runner.quick_load();
+        assert_eq!(runner.status, "Quick loaded");
+        assert_eq!(runner.pos, V2::make(3, 2));
+        assert_eq!(runner.moves(), 1);
+
+        runner.level = Level::new(9, 6);
+        runner.start();
+        runner.quick_load();
+        assert_eq!(runner.status, "Quick save is from a different version of this level");
+        assert_eq!(runner.level.width, 9);
+        assert_eq!(runner.moves(), 0);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::level::{Cell, Level, Trigger};
+use crate::vecmath::V2;
+
+/// Kind of the user action an edit belongs to. Consecutive edits of the same group, like
+/// individual steps of a paint stroke, are merged into a single undo step.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum EditGroup {
+    Single,
+    PaintStroke,
+    MouseStroke,
+    Text,
+}
+
+#[derive(Clone)]
+struct Markers {
+    p0: V2,
+    triggers: Vec<Trigger>,
+}
+
+impl Markers {
+    fn from(level: &Level) -> Markers {
+        Markers {
+            p0: level.p0,
+            triggers: level.triggers.clone(),
+        }
+    }
+
+    fn apply(&self, level: &mut Level) {
+        level.p0 = self.p0;
+        level.triggers = self.triggers.clone();
+    }
+}
+
+struct CellChange {
+    pos: V2,
+    old: Cell,
+    new: Cell,
+}
+
+struct Transaction {
+    id: u64,
+    group: EditGroup,
+    cells: Vec<CellChange>,
+    markers: Option<(Markers, Markers)>,
+    snapshot: Option<(Level, Level)>,
+}
+
+impl Transaction {
+    fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.markers.is_none() && self.snapshot.is_none()
+    }
+
+    fn undo(&self, level: &mut Level) {
+        if let Some((old, _)) = &self.snapshot {
+            *level = old.clone();
+            return;
+        }
+        for change in self.cells.iter().rev() {
+            level.set(change.pos, change.old);
+        }
+        if let Some((old, _)) = &self.markers {
+            old.apply(level);
+        }
+    }
+
+    fn redo(&self, level: &mut Level) {
+        if let Some((_, new)) = &self.snapshot {
+            *level = new.clone();
+            return;
+        }
+        for change in &self.cells {
+            level.set(change.pos, change.new);
+        }
+        if let Some((_, new)) = &self.markers {
+            new.apply(level);
+        }
+    }
+}
+
+/// Undo/redo history for the level editor.
+///
+/// Edits are recorded into an open transaction which gets committed either explicitly or when an
+/// edit from a different group starts.
+pub struct EditHistory {
+    undo: Vec<Transaction>,
+    redo: Vec<Transaction>,
+    open: Option<Transaction>,
+    next_id: u64,
+    limit: usize,
+    /// Revision the level is at once all the remaining undo steps are undone, changes when the
+    /// oldest steps get dropped because of `limit`.
+    base_revision: u64,
+}
+
+impl EditHistory {
+    pub fn new() -> EditHistory {
+        EditHistory {
+            undo: vec![],
+            redo: vec![],
+            open: None,
+            next_id: 1,
+            limit: 200,
+            base_revision: 0,
+        }
+    }
+
+    pub fn begin(&mut self, group: EditGroup, level: &Level) {
+        if let Some(open) = &self.open {
+            if open.group == group && group != EditGroup::Single {
+                return;
+            }
+        }
+        self.commit(level);
+        self.open = Some(Transaction {
+            id: self.next_id,
+            group,
+            cells: vec![],
+            markers: None,
+            snapshot: None,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn open_group(&self) -> Option<EditGroup> {
+        self.open.as_ref().map(|t| t.group)
+    }
+
+    /// Closes the open transaction, empty transactions are dropped.
+    pub fn commit(&mut self, level: &Level) {
+        if let Some(mut transaction) = self.open.take() {
+            if let Some((_, new)) = &mut transaction.markers {
+                *new = Markers::from(level);
+            }
+            if let Some((_, new)) = &mut transaction.snapshot {
+                *new = level.clone();
+            }
+            if transaction.is_empty() {
+                return;
+            }
+            self.undo.push(transaction);
+            self.redo.clear();
+            if self.undo.len() > self.limit {
+                let dropped = self.undo.remove(0);
+                self.base_revision = dropped.id;
+            }
+        }
+    }
+
+    /// Must be called before cell at `pos` is changed to `new`.
+    pub fn record_cell(&mut self, level: &Level, pos: V2, new: Cell) {
+        if !level.contains(pos) {
+            return;
+        }
+        if let Some(open) = &mut self.open {
+            open.cells.push(CellChange { pos, old: level[pos], new });
+        }
+    }
+
+    /// Must be called before changing p0 or triggers.
+    pub fn record_markers(&mut self, level: &Level) {
+        if let Some(open) = &mut self.open {
+            if open.markers.is_none() {
+                open.markers = Some((Markers::from(level), Markers::from(level)));
+            }
+        }
+    }
+
+    /// Must be called before operations that change the whole level, like resizing.
+    pub fn record_snapshot(&mut self, level: &Level) {
+        if let Some(open) = &mut self.open {
+            if open.snapshot.is_none() {
+                open.snapshot = Some((level.clone(), level.clone()));
+            }
+        }
+    }
+
+    pub fn undo(&mut self, level: &mut Level) -> bool {
+        self.commit(level);
+        match self.undo.pop() {
+            Some(transaction) => {
+                transaction.undo(level);
+                self.redo.push(transaction);
+                true
+            }
+            None => false
+        }
+    }
+
+    pub fn redo(&mut self, level: &mut Level) -> bool {
+        self.commit(level);
+        match self.redo.pop() {
+            Some(transaction) => {
+                transaction.redo(level);
+                self.undo.push(transaction);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Identifies the current state of the level, equal revisions mean equal level content as
+    /// long as all the changes were recorded. Uncommitted changes are not included.
+    pub fn revision(&self) -> u64 {
+        self.undo.last().map_or(self.base_revision, |t| t.id)
+    }
+
+    pub fn has_uncommitted(&self) -> bool {
+        self.open.as_ref().is_some_and(|t| !t.is_empty())
+    }
+
+    pub fn undo_depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_depth(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        EditHistory::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(c: char) -> Cell {
+        let mut cell = Cell::make_empty();
+        cell.letter = c;
+        cell
+    }
+
+    fn set(history: &mut EditHistory, level: &mut Level, pos: V2, c: char) {
+        history.record_cell(level, pos, letter(c));
+        level.set(pos, letter(c));
+    }
+
+    #[test]
+    fn stroke_is_single_step() {
+        let mut level = Level::new(10, 10);
+        let mut history = EditHistory::new();
+        for x in 0..5 {
+            history.begin(EditGroup::PaintStroke, &level);
+            set(&mut history, &mut level, V2::make(x, 0), 'a');
+        }
+        history.begin(EditGroup::Single, &level);
+        set(&mut history, &mut level, V2::make(0, 1), 'b');
+        history.commit(&level);
+        assert_eq!(history.undo_depth(), 2);
+
+        assert!(history.undo(&mut level));
+        assert_eq!(level[V2::make(0, 1)].letter, '\0');
+        assert_eq!(level[V2::make(4, 0)].letter, 'a');
+        assert!(history.undo(&mut level));
+        assert_eq!(level[V2::make(0, 0)].letter, '\0');
+        assert!(!history.undo(&mut level));
+
+        assert!(history.redo(&mut level));
+        assert_eq!(level[V2::make(3, 0)].letter, 'a');
+        assert_eq!(history.redo_depth(), 1);
+    }
+
+    #[test]
+    fn overwriting_same_cell_restores_original() {
+        let mut level = Level::new(10, 10);
+        let mut history = EditHistory::new();
+        history.begin(EditGroup::Text, &level);
+        set(&mut history, &mut level, V2::make(1, 1), 'a');
+        set(&mut history, &mut level, V2::make(1, 1), 'b');
+        history.undo(&mut level);
+        assert_eq!(level[V2::make(1, 1)].letter, '\0');
+        history.redo(&mut level);
+        assert_eq!(level[V2::make(1, 1)].letter, 'b');
+    }
+
+    #[test]
+    fn snapshot_and_markers() {
+        let mut level = Level::new(10, 10);
+        let mut history = EditHistory::new();
+        history.begin(EditGroup::Single, &level);
+        history.record_snapshot(&level);
+        level = Level::new(20, 5);
+        history.begin(EditGroup::Single, &level);
+        history.record_markers(&level);
+        level.p0 = V2::make(7, 3);
+        history.commit(&level);
+
+        history.undo(&mut level);
+        assert_eq!(level.p0, V2::make(2, 2));
+        assert_eq!(level.width, 20);
+        history.undo(&mut level);
+        assert_eq!(level.width, 10);
+        history.redo(&mut level);
+        history.redo(&mut level);
+        assert_eq!((level.width, level.p0), (20, V2::make(7, 3)));
+    }
+
+    #[test]
+    fn revision_after_undo() {
+        let mut level = Level::new(10, 10);
+        let mut history = EditHistory::new();
+        assert_eq!(history.revision(), 0);
+        history.begin(EditGroup::Single, &level);
+        set(&mut history, &mut level, V2::make(0, 0), 'a');
+        assert!(history.has_uncommitted());
+        history.commit(&level);
+        let saved = history.revision();
+        history.begin(EditGroup::Single, &level);
+        set(&mut history, &mut level, V2::make(1, 0), 'b');
+        history.commit(&level);
+        assert_ne!(history.revision(), saved);
+        history.undo(&mut level);
+        assert_eq!(history.revision(), saved);
+        history.undo(&mut level);
+        assert_eq!(history.revision(), 0);
+    }
+
+    #[test]
+    fn revision_after_trimming() {
+        let mut level = Level::new(10, 10);
+        let mut history = EditHistory::new();
+        history.limit = 3;
+        for x in 0..5 {
+            history.begin(EditGroup::Single, &level);
+            set(&mut history, &mut level, V2::make(x, 0), 'a');
+        }
+        history.commit(&level);
+        while history.undo(&mut level) {}
+        assert_eq!(level[V2::make(1, 0)].letter, 'a');
+        assert_ne!(history.revision(), 0);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut level = Level::new(10, 10);
+        let mut history = EditHistory::new();
+        history.begin(EditGroup::Single, &level);
+        set(&mut history, &mut level, V2::make(0, 0), 'a');
+        history.undo(&mut level);
+        assert_eq!(history.redo_depth(), 1);
+        history.begin(EditGroup::Single, &level);
+        set(&mut history, &mut level, V2::make(0, 0), 'b');
+        history.commit(&level);
+        assert_eq!(history.redo_depth(), 0);
+    }
+}
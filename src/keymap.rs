@@ -0,0 +1,785 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::path::PathBuf;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum KeyContext {
+    /// Every editor mode including text mode.
+    Editor,
+    /// Editor view and marker modes.
+    Pan,
+    /// Editor modes other than text mode, where letters are free for shortcuts.
+    Shortcuts,
+    View,
+    Text,
+    Paint,
+    Markers,
+    /// Play-testing from the editor.
+    Test,
+    Game,
+    Diff,
+}
+
+/// Contexts searched together by each part of the interface, earlier ones take precedence.
+pub const VIEW_KEYS: &[KeyContext] = &[KeyContext::View, KeyContext::Shortcuts, KeyContext::Pan, KeyContext::Editor];
+pub const TEXT_KEYS: &[KeyContext] = &[KeyContext::Text, KeyContext::Editor];
+pub const PAINT_KEYS: &[KeyContext] = &[KeyContext::Paint, KeyContext::Shortcuts, KeyContext::Editor];
+pub const MARKER_KEYS: &[KeyContext] = &[KeyContext::Markers, KeyContext::Shortcuts, KeyContext::Pan, KeyContext::Editor];
+/// Play-testing from the editor, keys not used by the editor reach the game.
+pub const TEST_KEYS: &[KeyContext] = &[KeyContext::Test, KeyContext::Game];
+pub const GAME_KEYS: &[KeyContext] = &[KeyContext::Game];
+/// Level diff viewer, borrows the scroll, layer and find keys of the editor.
+pub const DIFF_KEYS: &[KeyContext] = &[KeyContext::Diff, KeyContext::Editor, KeyContext::Pan, KeyContext::View];
+const LOOKUP_ORDERS: [&[KeyContext]; 7] = [VIEW_KEYS, TEXT_KEYS, PAINT_KEYS, MARKER_KEYS, TEST_KEYS, GAME_KEYS, DIFF_KEYS];
+
+/// Everything a key can be bound to. Names in the config file are the snake_case variant names.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Help,
+    Confirm,
+    Cancel,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    Undo,
+    Redo,
+    Quit,
+    ViewMode,
+    TextMode,
+    SetCorner,
+    PaintMode,
+    MarkerMode,
+    ToggleMinimap,
+    Test,
+    TestHere,
+    Save,
+    CycleLayer,
+    PanUp,
+    PanDown,
+    PanLeft,
+    PanRight,
+    ToggleTriggers,
+    CommandLine,
+    Find,
+    FindNext,
+    Copy,
+    Paste,
+    SaveStamp,
+    InsertStamp,
+    EditText,
+    Resize,
+    Crop,
+    Select,
+    CopySelection,
+    MoveSelection,
+    FillSelection,
+    FlipHorizontal,
+    FlipVertical,
+    Rotate,
+    Invert,
+    NewLine,
+    ToggleInsert,
+    LineStart,
+    LineEnd,
+    DeleteLetter,
+    Backspace,
+    PaintUp,
+    PaintDown,
+    PaintLeft,
+    PaintRight,
+    PaintHere,
+    PaintWhite,
+    PaintBlack,
+    PaintInvert,
+    PaintLightGrayText,
+    PaintDarkGrayText,
+    PaintGray,
+    PaintDarkGray,
+    FillBackground,
+    FillAll,
+    FillConnectivity,
+    CycleSymmetry,
+    SymmetryInvert,
+    Line,
+    Rectangle,
+    Ellipse,
+    ShapeLetter,
+    SetStart,
+    RemoveTriggers,
+    PlaceExit0,
+    PlaceExit1,
+    PlaceExit2,
+    TriggerId,
+    TriggerPanel,
+    NextTrigger,
+    PreviousTrigger,
+    AdoptTest,
+    SaveSolution,
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Restart,
+    QuickSave,
+    QuickLoad,
+    Pause,
+    ToggleView,
+    PreviousChange,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Category {
+    General,
+    Modes,
+    Navigation,
+    Display,
+    Selection,
+    Clipboard,
+    Text,
+    Paint,
+    Shapes,
+    Markers,
+    PlayTest,
+    Movement,
+    Game,
+}
+
+impl Category {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::General => "General",
+            Category::Modes => "Modes",
+            Category::Navigation => "Navigation",
+            Category::Display => "Display",
+            Category::Selection => "Selection",
+            Category::Clipboard => "Clipboard",
+            Category::Text => "Text",
+            Category::Paint => "Paint",
+            Category::Shapes => "Shapes",
+            Category::Markers => "Markers",
+            Category::PlayTest => "Play-test",
+            Category::Movement => "Movement",
+            Category::Game => "Game",
+        }
+    }
+}
+
+impl Action {
+    pub fn name(&self) -> String {
+        match serde_yaml::to_value(self) {
+            Ok(serde_yaml::Value::String(name)) => name,
+            _ => format!("{:?}", self),
+        }
+    }
+
+    pub fn category(&self) -> Category {
+        match self {
+            Action::Help | Action::Confirm | Action::Cancel | Action::Undo | Action::Redo | Action::Save |
+            Action::Quit | Action::CommandLine => Category::General,
+            Action::ViewMode | Action::TextMode | Action::EditText | Action::PaintMode | Action::MarkerMode |
+            Action::Test | Action::TestHere => Category::Modes,
+            Action::CursorUp | Action::CursorDown | Action::CursorLeft | Action::CursorRight |
+            Action::PanUp | Action::PanDown | Action::PanLeft | Action::PanRight |
+            Action::Find | Action::FindNext | Action::PreviousChange => Category::Navigation,
+            Action::ToggleView | Action::ToggleMinimap | Action::CycleLayer | Action::ToggleTriggers | Action::TriggerPanel => Category::Display,
+            Action::Select | Action::CopySelection | Action::MoveSelection | Action::FillSelection |
+            Action::FlipHorizontal | Action::FlipVertical | Action::Rotate | Action::Invert |
+            Action::Resize | Action::Crop => Category::Selection,
+            Action::Copy | Action::Paste | Action::SaveStamp | Action::InsertStamp => Category::Clipboard,
+            Action::SetCorner | Action::NewLine | Action::ToggleInsert | Action::LineStart | Action::LineEnd |
+            Action::DeleteLetter | Action::Backspace => Category::Text,
+            Action::PaintUp | Action::PaintDown | Action::PaintLeft | Action::PaintRight | Action::PaintHere |
+            Action::PaintWhite | Action::PaintBlack | Action::PaintInvert | Action::PaintLightGrayText |
+            Action::PaintDarkGrayText | Action::PaintGray | Action::PaintDarkGray | Action::FillBackground |
+            Action::FillAll | Action::FillConnectivity | Action::CycleSymmetry | Action::SymmetryInvert => Category::Paint,
+            Action::Line | Action::Rectangle | Action::Ellipse | Action::ShapeLetter => Category::Shapes,
+            Action::SetStart | Action::RemoveTriggers | Action::PlaceExit0 | Action::PlaceExit1 |
+            Action::PlaceExit2 | Action::TriggerId | Action::NextTrigger | Action::PreviousTrigger => Category::Markers,
+            Action::AdoptTest | Action::SaveSolution => Category::PlayTest,
+            Action::MoveUp | Action::MoveDown | Action::MoveLeft | Action::MoveRight => Category::Movement,
+            Action::Restart | Action::QuickSave | Action::QuickLoad | Action::Pause => Category::Game,
+        }
+    }
+
+    pub fn help(&self) -> &'static str {
+        match self {
+            Action::Help => "help",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::CursorUp => "cursor up",
+            Action::CursorDown => "cursor down",
+            Action::CursorLeft => "cursor left",
+            Action::CursorRight => "cursor right",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Quit => "quit",
+            Action::ViewMode => "view",
+            Action::TextMode => "text mode",
+            Action::SetCorner => "corner",
+            Action::PaintMode => "paint",
+            Action::MarkerMode => "markers",
+            Action::ToggleMinimap => "minimap",
+            Action::Test => "test",
+            Action::TestHere => "test here",
+            Action::Save => "save",
+            Action::CycleLayer => "layers",
+            Action::PanUp => "scroll up",
+            Action::PanDown => "scroll down",
+            Action::PanLeft => "scroll left",
+            Action::PanRight => "scroll right",
+            Action::ToggleTriggers => "toggle triggers",
+            Action::CommandLine => "command",
+            Action::Find => "find",
+            Action::FindNext => "next",
+            Action::Copy => "copy",
+            Action::Paste => "paste",
+            Action::SaveStamp => "save stamp",
+            Action::InsertStamp => "insert stamp",
+            Action::EditText => "text mode",
+            Action::Resize => "resize level",
+            Action::Crop => "crop",
+            Action::Select => "select rect",
+            Action::CopySelection => "copy selection here",
+            Action::MoveSelection => "move selection here",
+            Action::FillSelection => "fill",
+            Action::FlipHorizontal => "flip horizontal",
+            Action::FlipVertical => "flip vertical",
+            Action::Rotate => "rotate",
+            Action::Invert => "invert colors",
+            Action::NewLine => "new line",
+            Action::ToggleInsert => "insert/overwrite",
+            Action::LineStart => "line start",
+            Action::LineEnd => "line end",
+            Action::DeleteLetter => "delete",
+            Action::Backspace => "delete back",
+            Action::PaintUp => "paint up",
+            Action::PaintDown => "paint down",
+            Action::PaintLeft => "paint left",
+            Action::PaintRight => "paint right",
+            Action::PaintHere => "paint here",
+            Action::PaintWhite => "white",
+            Action::PaintBlack => "black",
+            Action::PaintInvert => "invert",
+            Action::PaintLightGrayText => "light gray text",
+            Action::PaintDarkGrayText => "dark gray text",
+            Action::PaintGray => "gray wall",
+            Action::PaintDarkGray => "dark gray wall",
+            Action::FillBackground => "fill background",
+            Action::FillAll => "fill background+text",
+            Action::FillConnectivity => "fill connectivity",
+            Action::CycleSymmetry => "symmetry",
+            Action::SymmetryInvert => "mirror inverted",
+            Action::Line => "line",
+            Action::Rectangle => "rectangle",
+            Action::Ellipse => "ellipse",
+            Action::ShapeLetter => "shape letter",
+            Action::SetStart => "level start",
+            Action::RemoveTriggers => "remove triggers",
+            Action::PlaceExit0 => "exit0",
+            Action::PlaceExit1 => "exit1",
+            Action::PlaceExit2 => "exit2",
+            Action::TriggerId => "trigger id",
+            Action::TriggerPanel => "trigger list",
+            Action::NextTrigger => "next trigger",
+            Action::PreviousTrigger => "previous trigger",
+            Action::AdoptTest => "keep this state",
+            Action::SaveSolution => "save moves as solution",
+            Action::MoveUp => "up",
+            Action::MoveDown => "down",
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::Restart => "restart",
+            Action::QuickSave => "quick save",
+            Action::QuickLoad => "quick load",
+            Action::Pause => "pause menu",
+            Action::ToggleView => "old/new/both",
+            Action::PreviousChange => "previous change",
+        }
+    }
+}
+
+/// Bindings matching the original hardcoded keys, in status bar order.
+const DEFAULT_BINDINGS: &[(KeyContext, Action, &[&str])] = &[
+    (KeyContext::Editor, Action::ViewMode, &["F2"]),
+    (KeyContext::Editor, Action::TextMode, &["F3"]),
+    (KeyContext::Editor, Action::SetCorner, &["F4"]),
+    (KeyContext::Editor, Action::PaintMode, &["F5"]),
+    (KeyContext::Editor, Action::MarkerMode, &["F6"]),
+    (KeyContext::Editor, Action::ToggleMinimap, &["F7"]),
+    (KeyContext::Editor, Action::Test, &["F8"]),
+    (KeyContext::Editor, Action::TestHere, &["shift+F8"]),
+    (KeyContext::Editor, Action::Save, &["F9"]),
+    (KeyContext::Editor, Action::CycleLayer, &["ctrl+l"]),
+    (KeyContext::Editor, Action::Undo, &["ctrl+z"]),
+    (KeyContext::Editor, Action::Redo, &["ctrl+y"]),
+    (KeyContext::Editor, Action::Quit, &["ctrl+q"]),
+    (KeyContext::Editor, Action::CursorUp, &["up"]),
+    (KeyContext::Editor, Action::CursorDown, &["down"]),
+    (KeyContext::Editor, Action::CursorLeft, &["left"]),
+    (KeyContext::Editor, Action::CursorRight, &["right"]),
+    (KeyContext::Pan, Action::PanUp, &["w"]),
+    (KeyContext::Pan, Action::PanDown, &["s"]),
+    (KeyContext::Pan, Action::PanLeft, &["a"]),
+    (KeyContext::Pan, Action::PanRight, &["d"]),
+    (KeyContext::Shortcuts, Action::Help, &["F1", "?"]),
+    (KeyContext::Shortcuts, Action::CommandLine, &[":"]),
+    (KeyContext::Shortcuts, Action::Find, &["/"]),
+    (KeyContext::Shortcuts, Action::ToggleTriggers, &["t"]),
+    (KeyContext::View, Action::FindNext, &["n"]),
+    (KeyContext::View, Action::EditText, &["e"]),
+    (KeyContext::View, Action::Resize, &["R"]),
+    (KeyContext::View, Action::Crop, &["C"]),
+    (KeyContext::View, Action::Select, &["m"]),
+    (KeyContext::View, Action::CopySelection, &["k"]),
+    (KeyContext::View, Action::MoveSelection, &["l"]),
+    (KeyContext::View, Action::FillSelection, &["0"]),
+    (KeyContext::View, Action::FlipHorizontal, &["h"]),
+    (KeyContext::View, Action::FlipVertical, &["v"]),
+    (KeyContext::View, Action::Rotate, &["o"]),
+    (KeyContext::View, Action::Invert, &["i"]),
+    (KeyContext::View, Action::Copy, &["y"]),
+    (KeyContext::View, Action::Paste, &["p"]),
+    (KeyContext::View, Action::SaveStamp, &["Y"]),
+    (KeyContext::View, Action::InsertStamp, &["P"]),
+    (KeyContext::View, Action::Confirm, &["enter", "space"]),
+    (KeyContext::View, Action::Cancel, &["esc"]),
+    (KeyContext::Text, Action::Help, &["F1"]),
+    (KeyContext::Text, Action::NewLine, &["enter"]),
+    (KeyContext::Text, Action::ToggleInsert, &["insert"]),
+    (KeyContext::Text, Action::DeleteLetter, &["delete"]),
+    (KeyContext::Text, Action::Backspace, &["backspace", "ctrl+h"]),
+    (KeyContext::Text, Action::LineStart, &["home"]),
+    (KeyContext::Text, Action::LineEnd, &["end"]),
+    (KeyContext::Text, Action::Paste, &["ctrl+v"]),
+    (KeyContext::Text, Action::CycleSymmetry, &["ctrl+e"]),
+    (KeyContext::Text, Action::Cancel, &["esc"]),
+    (KeyContext::Paint, Action::PaintWhite, &["z"]),
+    (KeyContext::Paint, Action::PaintBlack, &["x"]),
+    (KeyContext::Paint, Action::PaintInvert, &["c"]),
+    (KeyContext::Paint, Action::PaintLightGrayText, &["v"]),
+    (KeyContext::Paint, Action::PaintDarkGrayText, &["b"]),
+    (KeyContext::Paint, Action::PaintGray, &["n"]),
+    (KeyContext::Paint, Action::PaintDarkGray, &["m"]),
+    (KeyContext::Paint, Action::PaintHere, &["space"]),
+    (KeyContext::Paint, Action::PaintUp, &["w"]),
+    (KeyContext::Paint, Action::PaintDown, &["s"]),
+    (KeyContext::Paint, Action::PaintLeft, &["a"]),
+    (KeyContext::Paint, Action::PaintRight, &["d"]),
+    (KeyContext::Paint, Action::FillBackground, &["f"]),
+    (KeyContext::Paint, Action::FillAll, &["F"]),
+    (KeyContext::Paint, Action::FillConnectivity, &["g"]),
+    (KeyContext::Paint, Action::CycleSymmetry, &["ctrl+e"]),
+    (KeyContext::Paint, Action::SymmetryInvert, &["ctrl+r"]),
+    (KeyContext::Paint, Action::Line, &["l"]),
+    (KeyContext::Paint, Action::Rectangle, &["r"]),
+    (KeyContext::Paint, Action::Ellipse, &["o"]),
+    (KeyContext::Paint, Action::ShapeLetter, &["i"]),
+    (KeyContext::Paint, Action::Confirm, &["enter"]),
+    (KeyContext::Paint, Action::Cancel, &["esc"]),
+    (KeyContext::Markers, Action::SetStart, &["z"]),
+    (KeyContext::Markers, Action::PlaceExit1, &["x"]),
+    (KeyContext::Markers, Action::PlaceExit2, &["c"]),
+    (KeyContext::Markers, Action::PlaceExit0, &["v"]),
+    (KeyContext::Markers, Action::RemoveTriggers, &["backspace", "ctrl+h"]),
+    (KeyContext::Markers, Action::TriggerId, &["i"]),
+    (KeyContext::Markers, Action::TriggerPanel, &["p"]),
+    (KeyContext::Markers, Action::NextTrigger, &["tab"]),
+    (KeyContext::Markers, Action::PreviousTrigger, &["backtab"]),
+    (KeyContext::Markers, Action::Cancel, &["esc"]),
+    (KeyContext::Test, Action::Cancel, &["esc"]),
+    (KeyContext::Test, Action::AdoptTest, &["ctrl+k"]),
+    (KeyContext::Test, Action::SaveSolution, &["ctrl+s"]),
+    (KeyContext::Game, Action::Help, &["F1", "?"]),
+    (KeyContext::Game, Action::MoveUp, &["w", "up"]),
+    (KeyContext::Game, Action::MoveDown, &["s", "down"]),
+    (KeyContext::Game, Action::MoveLeft, &["a", "left"]),
+    (KeyContext::Game, Action::MoveRight, &["d", "right"]),
+    (KeyContext::Game, Action::Restart, &["r"]),
+    (KeyContext::Game, Action::QuickSave, &["F5"]),
+    (KeyContext::Game, Action::QuickLoad, &["F9"]),
+    (KeyContext::Game, Action::Pause, &["esc"]),
+    (KeyContext::Diff, Action::ToggleView, &["tab"]),
+    (KeyContext::Diff, Action::PreviousChange, &["N"]),
+    (KeyContext::Diff, Action::Quit, &["q"]),
+];
+
+/// Key combination independent of how the terminal reports shifted characters.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    /// Shift is folded into the character, "shift+r" and "R" are the same key.
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => Key {
+                code: KeyCode::Char(c.to_uppercase().next().unwrap_or(c)),
+                modifiers: modifiers - KeyModifiers::SHIFT,
+            },
+            KeyCode::BackTab => Key { code, modifiers: modifiers - KeyModifiers::SHIFT },
+            _ => Key { code, modifiers },
+        }
+    }
+
+    pub fn from_event(e: &KeyEvent) -> Key {
+        Key::new(e.code, e.modifiers)
+    }
+
+    pub fn is_printable(&self) -> bool {
+        matches!(self.code, KeyCode::Char(c) if !c.is_control()) && self.modifiers.is_empty()
+    }
+
+    /// Parses names like "ctrl+z", "shift+F8", "space" or "R".
+    pub fn parse(text: &str) -> Result<Key, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        while let Some((modifier, tail)) = rest.split_once('+').filter(|(_, tail)| !tail.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "shift" => KeyModifiers::SHIFT,
+                "alt" => KeyModifiers::ALT,
+                _ => return Err(format!("Unknown modifier '{}' in '{}'", modifier, text)),
+            };
+            rest = tail;
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n) if (1..=24).contains(&n) => KeyCode::F(n),
+                    _ => return Err(format!("Unknown key '{}'", text)),
+                },
+            },
+        };
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Key, String> {
+        Key::parse(&text)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "backtab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Insert => write!(f, "insert"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Binding {
+    pub context: KeyContext,
+    pub action: Action,
+    pub keys: Vec<Key>,
+}
+
+/// Maps keys to actions, loaded from `keys.yaml` in the config directory. The file maps action
+/// names to lists of keys and replaces the default keys of those actions, e.g.
+/// `undo: [ctrl+z, u]`. Keys which type characters never leave text mode.
+#[derive(Clone)]
+pub struct Keymap {
+    pub bindings: Vec<Binding>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            bindings: DEFAULT_BINDINGS.iter()
+                .map(|(context, action, keys)| Binding {
+                    context: *context,
+                    action: *action,
+                    keys: keys.iter().map(|key| Key::parse(key).expect("invalid default key")).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Keymap {
+    pub fn path() -> Option<PathBuf> {
+        storage::config_dir().map(|dir| dir.join("keys.yaml"))
+    }
+
+    /// Default bindings with the overrides from the user config file, along with a message for
+    /// the user when the file can't be used or its keys collide. A missing file is not an error.
+    pub fn load() -> (Keymap, Option<String>) {
+        let mut keymap = Keymap::default();
+        let path = match Keymap::path().filter(|path| path.is_file()) {
+            Some(path) => path,
+            None => return (keymap, None),
+        };
+        match storage::load_yaml(&path) {
+            Ok(overrides) => {
+                let conflicts = keymap.apply(&overrides);
+                let message = (!conflicts.is_empty())
+                    .then(|| format!("Key binding conflicts: {}", conflicts.join(", ")));
+                (keymap, message)
+            }
+            Err(e) => (keymap, Some(format!("Failed to load key bindings: {}", e))),
+        }
+    }
+
+    /// Replaces the keys of the overridden actions. Text mode bindings only take the keys which
+    /// don't type a character and keep their defaults if none are left. Returns the collisions
+    /// which weren't there with the default bindings.
+    pub fn apply(&mut self, overrides: &HashMap<Action, Vec<Key>>) -> Vec<String> {
+        for binding in &mut self.bindings {
+            if let Some(keys) = overrides.get(&binding.action) {
+                if binding.context != KeyContext::Text {
+                    binding.keys = keys.clone();
+                    continue;
+                }
+                let keys: Vec<Key> = keys.iter().copied().filter(|key| !key.is_printable()).collect();
+                if !keys.is_empty() {
+                    binding.keys = keys;
+                }
+            }
+        }
+        let defaults = Keymap::default().conflicts();
+        self.conflicts().into_iter().filter(|conflict| !defaults.contains(conflict)).collect()
+    }
+
+    /// Keys bound to several actions in contexts which are searched together, like
+    /// "z: set_start hides pan_up".
+    fn conflicts(&self) -> Vec<String> {
+        let mut result: Vec<String> = vec![];
+        for contexts in LOOKUP_ORDERS {
+            let bindings: Vec<&Binding> = contexts.iter()
+                .flat_map(|context| self.bindings.iter().filter(move |binding| binding.context == *context))
+                .collect();
+            for (i, binding) in bindings.iter().enumerate() {
+                for key in &binding.keys {
+                    // the first binding of a key wins
+                    let winner = match bindings[..i].iter().find(|other| other.keys.contains(key)) {
+                        Some(winner) if winner.action != binding.action => winner,
+                        _ => continue,
+                    };
+                    let conflict = format!("{}: {} hides {}", key, winner.action.name(), binding.action.name());
+                    if !result.contains(&conflict) {
+                        result.push(conflict);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    pub fn action(&self, contexts: &[KeyContext], e: &Event) -> Option<Action> {
+        let key = match e {
+            Event::Key(key) => Key::from_event(key),
+            _ => return None,
+        };
+        contexts.iter().find_map(|context| {
+            self.bindings.iter()
+                .find(|binding| binding.context == *context && binding.keys.contains(&key))
+                .map(|binding| binding.action)
+        })
+    }
+
+    /// Keys of an action like "w/up", empty when unbound.
+    pub fn keys_text(&self, context: KeyContext, action: Action) -> String {
+        self.bindings.iter()
+            .filter(|binding| binding.context == context && binding.action == action)
+            .flat_map(|binding| binding.keys.iter().map(|key| key.to_string()))
+            .collect::<Vec<String>>()
+            .join("/")
+    }
+
+    /// Status bar help like "[F2] view [F3] text mode" for the given actions.
+    pub fn describe(&self, context: KeyContext, actions: &[Action]) -> String {
+        actions.iter()
+            .map(|action| (self.keys_text(context, *action), action.help()))
+            .filter(|(keys, _)| !keys.is_empty())
+            .map(|(keys, help)| format!("[{}] {} ", keys, help))
+            .collect()
+    }
+
+    /// Help overlay text for the contexts, grouped by category. Keys shadowed by an earlier
+    /// context are left out since they never reach the later one.
+    pub fn help_lines(&self, contexts: &[KeyContext]) -> Vec<String> {
+        let mut seen: Vec<Key> = vec![];
+        let mut entries: Vec<(Category, String, Action)> = vec![];
+        for context in contexts {
+            let mut taken = vec![];
+            for binding in self.bindings.iter().filter(|binding| binding.context == *context) {
+                let keys: Vec<String> = binding.keys.iter()
+                    .filter(|key| !seen.contains(key))
+                    .map(|key| key.to_string())
+                    .collect();
+                taken.extend(binding.keys.iter().copied());
+                if !keys.is_empty() && !entries.iter().any(|(_, _, action)| *action == binding.action) {
+                    entries.push((binding.action.category(), keys.join("/"), binding.action));
+                }
+            }
+            seen.extend(taken);
+        }
+        entries.sort_by_key(|(category, _, _)| *category);
+        let width = entries.iter().map(|(_, keys, _)| keys.chars().count()).max().unwrap_or(0);
+        let mut lines = vec![];
+        for (i, (category, keys, action)) in entries.iter().enumerate() {
+            if i == 0 || entries[i - 1].0 != *category {
+                if i > 0 {
+                    lines.push(String::new());
+                }
+                lines.push(category.name().to_string());
+            }
+            lines.push(format!("  {:<width$}  {}", keys, action.help(), width = width));
+        }
+        lines
+    }
+
+    pub fn help(&self, context: KeyContext) -> String {
+        let actions: Vec<Action> = self.bindings.iter()
+            .filter(|binding| binding.context == context)
+            .map(|binding| binding.action)
+            .collect();
+        self.describe(context, &actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_event(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent { code, modifiers })
+    }
+
+    #[test]
+    fn key_names() {
+        assert_eq!(Key::parse("ctrl+z"), Ok(Key::new(KeyCode::Char('z'), KeyModifiers::CONTROL)));
+        assert_eq!(Key::parse("shift+r"), Key::parse("R"));
+        assert_eq!(Key::parse("shift+F8"), Ok(Key::new(KeyCode::F(8), KeyModifiers::SHIFT)));
+        assert_eq!(Key::parse("+"), Ok(Key::new(KeyCode::Char('+'), KeyModifiers::NONE)));
+        assert_eq!(Key::parse("ctrl++"), Ok(Key::new(KeyCode::Char('+'), KeyModifiers::CONTROL)));
+        assert!(Key::parse("F99").is_err());
+        assert!(Key::parse("hyper+x").is_err());
+        for name in ["ctrl+z", "shift+F8", "space", "R", "backtab", "F2", ":"] {
+            assert_eq!(Key::parse(name).unwrap().to_string(), name);
+        }
+    }
+
+    #[test]
+    fn lookup() {
+        let mut keymap = Keymap::default();
+        let shifted_r = key_event(KeyCode::Char('r'), KeyModifiers::SHIFT);
+        assert_eq!(keymap.action(&[KeyContext::View, KeyContext::Editor], &shifted_r), Some(Action::Resize));
+        let w = key_event(KeyCode::Char('w'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(&[KeyContext::Paint, KeyContext::Pan], &w), Some(Action::PaintUp));
+        assert_eq!(keymap.action(&[KeyContext::Text, KeyContext::Editor], &w), None);
+        assert_eq!(keymap.keys_text(KeyContext::Game, Action::MoveUp), "w/up");
+
+        let overrides: HashMap<Action, Vec<Key>> = serde_yaml::from_str("move_up: [z, up]\ncancel: [u]").unwrap();
+        assert!(keymap.apply(&overrides).is_empty());
+        assert_eq!(keymap.action(&[KeyContext::Game], &w), None);
+        let z = key_event(KeyCode::Char('z'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(&[KeyContext::Game], &z), Some(Action::MoveUp));
+        assert_eq!(keymap.describe(KeyContext::Paint, &[Action::Cancel]), "[u] cancel ");
+        assert!(serde_yaml::from_str::<HashMap<Action, Vec<Key>>>("jump: [j]").is_err());
+
+        let overrides: HashMap<Action, Vec<Key>> = serde_yaml::from_str("toggle_view: [x]").unwrap();
+        assert!(keymap.apply(&overrides).is_empty());
+        let x = key_event(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(DIFF_KEYS, &x), Some(Action::ToggleView));
+        assert_eq!(keymap.describe(KeyContext::Diff, &[Action::ToggleView]), "[x] old/new/both ");
+    }
+
+    #[test]
+    fn help_overlay() {
+        let keymap = Keymap::default();
+        let lines = keymap.help_lines(&[KeyContext::Test, KeyContext::Game]);
+        assert_eq!(lines[0], "General");
+        assert!(lines.contains(&"  esc      cancel".to_string()));
+        // esc goes to the play-test, not to the pause menu
+        assert!(!lines.iter().any(|line| line.contains("pause")));
+        let movement = lines.iter().position(|line| line == "Movement").unwrap();
+        assert!(movement < lines.iter().position(|line| line == "Game").unwrap());
+        assert_eq!(lines[movement + 1], "  w/up     up");
+    }
+
+    #[test]
+    fn text_mode_keeps_typing_keys() {
+        let mut keymap = Keymap::default();
+        let overrides: HashMap<Action, Vec<Key>> = serde_yaml::from_str("cancel: [u]\nhelp: [j, F1]\nundo: [ctrl+u]").unwrap();
+        assert!(keymap.apply(&overrides).is_empty());
+        let u = key_event(KeyCode::Char('u'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(VIEW_KEYS, &u), Some(Action::Cancel));
+        assert_eq!(keymap.action(TEXT_KEYS, &u), None);
+        assert_eq!(keymap.keys_text(KeyContext::Text, Action::Cancel), "esc");
+        assert_eq!(keymap.keys_text(KeyContext::Text, Action::Help), "F1");
+        assert_eq!(keymap.keys_text(KeyContext::Shortcuts, Action::Help), "j/F1");
+        let ctrl_u = key_event(KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(TEXT_KEYS, &ctrl_u), Some(Action::Undo));
+    }
+
+    #[test]
+    fn override_conflicts() {
+        assert!(Keymap::default().apply(&HashMap::new()).is_empty());
+        let mut keymap = Keymap::default();
+        let overrides: HashMap<Action, Vec<Key>> = serde_yaml::from_str("pan_up: [z]").unwrap();
+        assert_eq!(keymap.apply(&overrides), vec!["z: set_start hides pan_up".to_string()]);
+        let mut keymap = Keymap::default();
+        let overrides: HashMap<Action, Vec<Key>> = serde_yaml::from_str("paint_white: [ctrl+z]").unwrap();
+        assert_eq!(keymap.apply(&overrides), vec!["ctrl+z: paint_white hides undo".to_string()]);
+    }
+}
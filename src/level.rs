@@ -1,8 +1,9 @@
 use std::ops::{Index, IndexMut};
+use std::path::PathBuf;
 use crate::vecmath::{Rectangle, V2};
 use serde::{Serialize, Deserialize};
 
-#[derive(Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+#[derive(Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Debug)]
 pub enum CellColor {
     White,
     Black,
@@ -10,7 +11,7 @@ pub enum CellColor {
     DarkGray,
 }
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Cell {
     pub letter: char,
     pub background: CellColor,
@@ -52,6 +53,9 @@ pub struct Level {
     pub p0: V2,
     pub triggers: Vec<Trigger>,
     pub data: Vec<Vec<Cell>>,
+    /// Reference solution, directions of the player moves starting from `p0`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub solution: Vec<V2>,
 }
 
 impl Level {
@@ -62,6 +66,7 @@ impl Level {
             height,
             p0: V2::make(2, 2),
             triggers: vec![],
+            solution: vec![],
         };
     }
 
@@ -82,6 +87,154 @@ impl Level {
     pub fn bounds(&self) -> Rectangle {
         Rectangle{pos: V2::make(0, 0), size: self.size()}
     }
+
+    /// Changes the level size, old content is placed at `offset` within the new level.
+    /// `p0` and triggers move together with the content.
+    pub fn resize_with_offset(&mut self, size: V2, offset: V2) {
+        let mut data = vec![vec![Cell::make_empty(); size.x as usize]; size.y as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let target = V2::make(x, y) + offset;
+                if target.x >= 0 && target.x < size.x && target.y >= 0 && target.y < size.y {
+                    data[target.y as usize][target.x as usize] = self.data[y as usize][x as usize];
+                }
+            }
+        }
+        self.data = data;
+        self.width = size.x;
+        self.height = size.y;
+        self.p0 = self.p0 + offset;
+        for trigger in &mut self.triggers {
+            trigger.pos = trigger.pos + offset;
+        }
+    }
+
+    pub fn resize(&mut self, size: V2, anchor: Anchor) {
+        let offset = anchor.offset(self.size(), size);
+        self.resize_with_offset(size, offset);
+    }
+
+    /// Smallest area containing all the non-empty cells, `p0` and triggers.
+    pub fn content_bounds(&self) -> Option<Rectangle> {
+        let mut points: Vec<V2> = vec![self.p0];
+        points.extend(self.triggers.iter().map(|trigger| trigger.pos));
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.data[y as usize][x as usize];
+                if !cell.empty() || cell.background != EMPTY_CELL.background {
+                    points.push(V2::make(x, y));
+                }
+            }
+        }
+        let first = *points.first()?;
+        Some(points.iter().fold(Rectangle { pos: first, size: V2::make(1, 1) }, |rect, p| {
+            Rectangle::from_corners(
+                V2::make(rect.left().min(p.x), rect.top().min(p.y)),
+                V2::make(rect.right().max(p.x), rect.bottom().max(p.y)))
+        }))
+    }
+}
+
+/// Rectangular piece of a level with the triggers inside it, positions are relative to the
+/// top-left corner. Used for the editor clipboard and stamp files.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Stamp {
+    pub width: i32,
+    pub height: i32,
+    pub triggers: Vec<Trigger>,
+    pub data: Vec<Vec<Cell>>,
+}
+
+impl Stamp {
+    pub fn copy_from(level: &Level, rect: Rectangle) -> Stamp {
+        let data = (rect.top()..=rect.bottom())
+            .map(|y| (rect.left()..=rect.right()).map(|x| level[V2::make(x, y)]).collect())
+            .collect();
+        let triggers = level.triggers.iter()
+            .filter(|trigger| rect.contains(trigger.pos))
+            .map(|trigger| Trigger { pos: trigger.pos - rect.pos, id: trigger.id.clone() })
+            .collect();
+        Stamp {
+            width: rect.width(),
+            height: rect.height(),
+            triggers,
+            data,
+        }
+    }
+
+    pub fn size(&self) -> V2 {
+        V2::make(self.width, self.height)
+    }
+
+    /// Cells with positions relative to the top-left corner.
+    pub fn cells(&self) -> impl Iterator<Item=(V2, Cell)> + '_ {
+        self.data.iter().enumerate().flat_map(|(y, row)| {
+            row.iter().enumerate().map(move |(x, cell)| (V2::make(x as i32, y as i32), *cell))
+        })
+    }
+
+    /// Letters as lines of text, empty cells become spaces and trailing ones are dropped.
+    pub fn text(&self) -> String {
+        self.data.iter()
+            .map(|row| row.iter()
+                .map(|cell| if cell.empty() { ' ' } else { cell.letter })
+                .collect::<String>()
+                .trim_end()
+                .to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    pub const NAMES: [&'static str; 9] = ["top-left", "top", "top-right", "left", "center", "right",
+        "bottom-left", "bottom", "bottom-right"];
+
+    /// Accepts names like "top-left" as well as short forms like "tl".
+    pub fn parse(text: &str) -> Option<Anchor> {
+        match text.to_lowercase().as_str() {
+            "top-left" | "tl" => Some(Anchor::TopLeft),
+            "top" | "t" => Some(Anchor::Top),
+            "top-right" | "tr" => Some(Anchor::TopRight),
+            "left" | "l" => Some(Anchor::Left),
+            "center" | "centre" | "c" => Some(Anchor::Center),
+            "right" | "r" => Some(Anchor::Right),
+            "bottom-left" | "bl" => Some(Anchor::BottomLeft),
+            "bottom" | "b" => Some(Anchor::Bottom),
+            "bottom-right" | "br" => Some(Anchor::BottomRight),
+            _ => None
+        }
+    }
+
+    /// Position of the old content within the new size.
+    pub fn offset(&self, old: V2, new: V2) -> V2 {
+        let (fx, fy) = match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        };
+        let diff = new - old;
+        V2::make(diff.x * fx / 2, diff.y * fy / 2)
+    }
 }
 
 impl Index<V2> for Level {
@@ -96,5 +249,106 @@ impl Index<V2> for Level {
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct LevelList {
-    pub files: Vec<String>
-}
\ No newline at end of file
+    pub files: Vec<String>,
+    /// Folder the entries in `files` are relative to.
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+impl LevelList {
+    pub fn path(&self, index: usize) -> Option<PathBuf> {
+        self.files.get(index).map(|file| self.root.join(file))
+    }
+
+    pub fn index_of(&self, file: &str) -> Option<usize> {
+        self.files.iter().position(|f| f == file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter(level: &mut Level, pos: V2, c: char) {
+        let mut cell = Cell::make_empty();
+        cell.letter = c;
+        level.set(pos, cell);
+    }
+
+    #[test]
+    fn resize_moves_markers() {
+        let mut level = Level::new(10, 10);
+        letter(&mut level, V2::make(5, 5), 'a');
+        level.triggers.push(Trigger { pos: V2::make(9, 9), id: "exit0".into() });
+        level.resize(V2::make(20, 14), Anchor::Center);
+        assert_eq!(level.size(), V2::make(20, 14));
+        assert_eq!(level[V2::make(10, 7)].letter, 'a');
+        assert_eq!(level.p0, V2::make(7, 4));
+        assert_eq!(level.triggers[0].pos, V2::make(14, 11));
+
+        level.resize(V2::make(15, 14), Anchor::BottomRight);
+        assert_eq!(level[V2::make(5, 7)].letter, 'a');
+        level.resize(V2::make(6, 8), Anchor::TopLeft);
+        assert_eq!(level[V2::make(5, 7)].letter, 'a');
+        assert_eq!(level.data.len(), 8);
+        assert!(level.data.iter().all(|row| row.len() == 6));
+    }
+
+    #[test]
+    fn crop_bounds() {
+        let mut level = Level::new(20, 20);
+        level.p0 = V2::make(4, 3);
+        letter(&mut level, V2::make(10, 12), 'x');
+        let mut wall = Cell::make_empty();
+        wall.background = CellColor::White;
+        level.set(V2::make(2, 8), wall);
+        let bounds = level.content_bounds().unwrap();
+        assert_eq!((bounds.pos, bounds.size), (V2::make(2, 3), V2::make(9, 10)));
+    }
+
+    #[test]
+    fn stamp_text() {
+        let mut level = Level::new(6, 3);
+        letter(&mut level, V2::make(1, 1), 'h');
+        letter(&mut level, V2::make(3, 1), 'i');
+        letter(&mut level, V2::make(0, 2), 'x');
+        let stamp = Stamp::copy_from(&level, Rectangle { pos: V2::make(0, 1), size: V2::make(5, 2) });
+        assert_eq!(stamp.text(), " h i\nx");
+    }
+
+    #[test]
+    fn anchor_names() {
+        for name in Anchor::NAMES {
+            assert!(Anchor::parse(name).is_some());
+        }
+        assert_eq!(Anchor::parse("centre"), Some(Anchor::Center));
+        assert_eq!(Anchor::parse("middle"), None);
+    }
+
+    #[test]
+    fn stamp_copy() {
+        let mut level = Level::new(10, 10);
+        letter(&mut level, V2::make(3, 4), 'q');
+        level.triggers.push(Trigger { pos: V2::make(4, 4), id: "exit1".into() });
+        level.triggers.push(Trigger { pos: V2::make(8, 8), id: "exit2".into() });
+        let stamp = Stamp::copy_from(&level, Rectangle { pos: V2::make(3, 3), size: V2::make(2, 2) });
+        assert_eq!(stamp.size(), V2::make(2, 2));
+        assert_eq!(stamp.triggers.len(), 1);
+        assert_eq!(stamp.triggers[0].pos, V2::make(1, 1));
+        let cells: Vec<(V2, Cell)> = stamp.cells().collect();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[2].0, V2::make(0, 1));
+        assert_eq!(cells[2].1.letter, 'q');
+    }
+
+    #[test]
+    fn solution_is_optional() {
+        let mut level = Level::new(5, 5);
+        let text = serde_yaml::to_string(&level).unwrap();
+        assert!(!text.contains("solution"));
+        assert!(serde_yaml::from_str::<Level>(&text).unwrap().solution.is_empty());
+        level.solution = vec![V2::make(1, 0), V2::make(0, -1)];
+        let loaded: Level = serde_yaml::from_str(&serde_yaml::to_string(&level).unwrap()).unwrap();
+        assert_eq!(loaded.solution, level.solution);
+    }
+}
@@ -10,18 +10,30 @@ use crossterm::{
     style,
 };
 use std::io::{ErrorKind, Stdout, Write};
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use crossterm::style::style;
 use std::thread::current;
 use clap::{App, Arg};
 use crossterm::terminal::ClearType;
+use crate::diff::LevelDiff;
 use crate::game::MultiLevelRunner;
-use crate::level::LevelList;
+use crate::level::{Level, LevelList};
+use crate::stats::{LevelStats, Stats};
 
 pub mod vecmath;
 pub mod ui;
 pub mod game;
 pub mod level;
+pub mod storage;
+pub mod progress;
+pub mod stats;
+pub mod settings;
+pub mod history;
+pub mod tools;
+pub mod commands;
+pub mod keymap;
+pub mod diff;
 
 
 fn run_empty_editor() -> std::io::Result<()>
@@ -41,6 +53,17 @@ fn editor_for_file(path: &str) -> std::io::Result<()>
     let mut stdout = stdout();
     let mut ui = ui::UiContext::create(&mut stdout).unwrap();
     let mut editor = game::LevelEditor::new_from_path(&mut ui, file_path)?;
+    if editor.autosave_newer() {
+        print!("Found unsaved changes of '{}' from an earlier session. Restore them? [y/N] ", path);
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+        if answer.trim().eq_ignore_ascii_case("y") {
+            if let Err(e) = editor.restore_autosave() {
+                eprintln!("Failed to restore autosave: {}", e);
+            }
+        }
+    }
 
     enable_raw_mode()?;
     execute!(ui.stdout, crossterm::terminal::EnterAlternateScreen, crossterm::event::EnableMouseCapture)?;
@@ -56,10 +79,11 @@ fn good_level_path(path: &Path) -> bool {
     return pb.exists() && pb.is_dir();
 }
 
-fn play_levels() -> std::io::Result<()>
+fn find_levels() -> std::io::Result<LevelList>
 {
     let mut levels = LevelList{
-        files: vec!["levels/l1".into(), "levels/l2".into()]
+        files: vec!["levels/l1".into(), "levels/l2".into()],
+        root: PathBuf::new(),
     };
 
 
@@ -92,25 +116,98 @@ fn play_levels() -> std::io::Result<()>
             }
         }
 
-        for path in &mut levels.files {
-            let p2 = top_folder.join(&path);
-            path.replace_range(.., p2.to_str().unwrap());
-        }
+        levels.root = top_folder;
     }
+    Ok(levels)
+}
+
+fn play_levels() -> std::io::Result<()>
+{
+    let levels = find_levels()?;
     let mut stdout = stdout();
     let mut ui = ui::UiContext::create(&mut stdout).unwrap();
-    let mut runner = MultiLevelRunner::new(&mut ui, levels);
 
     enable_raw_mode()?;
     execute!(ui.stdout, crossterm::terminal::EnterAlternateScreen)?;
 
-    runner.start_next_level();
+    let mut runner = MultiLevelRunner::new(&mut ui, levels);
+
     let res = ui.run(&mut runner);
     ui.restore_normal();
     res
 }
 
 
+fn print_stats_row(name: &str, stats: &LevelStats) {
+    let time = stats.best_time_ms.map_or("-".into(), stats::format_time);
+    let moves = stats.fewest_moves.map_or("-".into(), |v| v.to_string());
+    let pushes = stats.fewest_pushes.map_or("-".into(), |v| v.to_string());
+    println!("{:<16} {:>8} {:>8} {:>6} {:>9} {:>6} {:>6}  {}", name, stats.attempts, stats.restarts,
+             stats.completions, time, moves, pushes, stats.exits.join(", "));
+}
+
+fn print_stats() -> std::io::Result<()>
+{
+    let stats = Stats::load();
+    println!("level            attempts restarts   done best time  moves pushes  exits");
+    let mut current = HashSet::new();
+    if let Ok(levels) = find_levels() {
+        for (i, name) in levels.files.iter().enumerate() {
+            let path = levels.path(i).unwrap();
+            let level = match storage::load_yaml::<Level>(&path) {
+                Ok(level) => level,
+                Err(e) => {
+                    eprintln!("Failed to load level '{}': {}", path.to_string_lossy(), e);
+                    continue;
+                }
+            };
+            let hash = stats::level_hash(&level);
+            print_stats_row(name, stats.get(&hash).unwrap_or(&LevelStats::default()));
+            current.insert(hash);
+        }
+    }
+    let outdated: Vec<_> = stats.levels.iter()
+        .filter(|(hash, _)| !current.contains(*hash))
+        .collect();
+    if !outdated.is_empty() {
+        println!("\nRecords of levels which have changed or are no longer part of the game:");
+        for (hash, level_stats) in outdated {
+            print_stats_row(&format!("{} {}", level_stats.name, &hash[..6]), level_stats);
+        }
+    }
+    Ok(())
+}
+
+fn load_level(path: &str) -> std::io::Result<Level> {
+    storage::load_yaml(Path::new(path)).map_err(|e| {
+        eprintln!("Failed to load level '{}': {}", path, e);
+        e
+    })
+}
+
+fn diff_levels(old_path: &str, new_path: &str, view: bool) -> std::io::Result<()>
+{
+    let old = load_level(old_path)?;
+    let new = load_level(new_path)?;
+    if view {
+        let mut stdout = stdout();
+        let mut ui = ui::UiContext::create(&mut stdout).unwrap();
+        enable_raw_mode()?;
+        execute!(ui.stdout, crossterm::terminal::EnterAlternateScreen)?;
+        let mut viewer = game::DiffViewer::new(&mut ui, (old_path.into(), new_path.into()), old, new);
+        let res = ui.run(&mut viewer);
+        ui.restore_normal();
+        return res;
+    }
+    let diff = LevelDiff::compare(&old, &new);
+    if diff.is_empty() {
+        println!("Levels are the same");
+    }
+    for line in diff.report(&old, &new) {
+        println!("{}", line);
+    }
+    Ok(())
+}
 
 fn main() -> Result<()> {
     let matches = App::new("GGJ22-kiwi")
@@ -125,6 +222,25 @@ fn main() -> Result<()> {
                     .required(true))
 
         )
+        .subcommand(
+            App::new("stats")
+                .about("Show level statistics")
+        )
+        .subcommand(
+            App::new("diff")
+                .about("Show the differences between two level files")
+                .arg(Arg::new("old")
+                    .help("Original level file")
+                    .takes_value(true)
+                    .required(true))
+                .arg(Arg::new("new")
+                    .help("Changed level file")
+                    .takes_value(true)
+                    .required(true))
+                .arg(Arg::new("view")
+                    .long("view")
+                    .help("Show both levels in the terminal with the changed cells highlighted"))
+        )
         .get_matches();
 
     let mut subcommand = matches.subcommand();
@@ -134,6 +250,14 @@ fn main() -> Result<()> {
             editor_for_file(path);
             Ok(())
         }
+        Some(("stats", _)) => {
+            print_stats()
+        }
+        Some(("diff", cmd)) => {
+            let old = cmd.value_of("old").ok_or(ErrorKind::Other)?;
+            let new = cmd.value_of("new").ok_or(ErrorKind::Other)?;
+            diff_levels(old, new, cmd.is_present("view"))
+        }
         _ =>  {
             play_levels()
         }
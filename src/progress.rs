@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use crate::level::LevelList;
+use crate::storage;
+
+/// Completion record of a single level. Levels are identified by their entry in
+/// `levels/list.yaml` instead of index so that reordering or adding levels doesn't
+/// shift the progress to wrong levels.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LevelProgress {
+    pub level: String,
+    pub exit: String,
+    #[serde(default)]
+    pub best_moves: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Progress {
+    #[serde(default)]
+    pub furthest: Option<String>,
+    #[serde(default)]
+    pub completed: Vec<LevelProgress>,
+}
+
+impl Progress {
+    pub fn path() -> Option<PathBuf> {
+        storage::data_file("progress.yaml")
+    }
+
+    /// Missing or unreadable save file is treated as a fresh start.
+    pub fn load() -> Progress {
+        Progress::path()
+            .and_then(|path| storage::load_yaml(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        match Progress::path() {
+            Some(path) => storage::save_yaml(&path, self),
+            None => Err(std::io::ErrorKind::NotFound.into())
+        }
+    }
+
+    pub fn get(&self, level: &str) -> Option<&LevelProgress> {
+        self.completed.iter().find(|record| record.level == level)
+    }
+
+    pub fn is_completed(&self, level: &str) -> bool {
+        self.get(level).is_some()
+    }
+
+    pub fn record_completion(&mut self, level: &str, exit: &str, moves: u32) {
+        let best_moves = match self.get(level).and_then(|record| record.best_moves) {
+            Some(best) => best.min(moves),
+            None => moves,
+        };
+        self.completed.retain(|record| record.level != level);
+        self.completed.push(LevelProgress {
+            level: level.into(),
+            exit: exit.into(),
+            best_moves: Some(best_moves),
+        });
+    }
+
+    /// Index of the furthest level reached, taking into account only the levels which
+    /// are still part of the list.
+    pub fn furthest_index(&self, levels: &LevelList) -> Option<usize> {
+        if levels.files.is_empty() {
+            return None;
+        }
+        let furthest = self.furthest.as_ref()
+            .and_then(|name| levels.index_of(name));
+        let after_completed = self.completed.iter()
+            .filter_map(|record| levels.index_of(&record.level))
+            .map(|i| (i + 1).min(levels.files.len() - 1))
+            .max();
+        furthest.max(after_completed)
+    }
+
+    pub fn reach(&mut self, levels: &LevelList, index: usize) {
+        if let Some(name) = levels.files.get(index) {
+            if !matches!(self.furthest_index(levels), Some(furthest) if furthest >= index) {
+                self.furthest = Some(name.clone());
+            }
+        }
+    }
+
+    /// Level to resume from, `None` if there is nothing to continue.
+    pub fn continue_index(&self, levels: &LevelList) -> Option<usize> {
+        self.furthest_index(levels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(files: &[&str]) -> LevelList {
+        LevelList {
+            files: files.iter().map(|s| s.to_string()).collect(),
+            root: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn fresh_progress_has_nothing_to_continue() {
+        let levels = list(&["l1", "l2"]);
+        assert_eq!(Progress::default().continue_index(&levels), None);
+    }
+
+    #[test]
+    fn continue_from_furthest() {
+        let levels = list(&["l1", "l2", "l3"]);
+        let mut progress = Progress::default();
+        progress.reach(&levels, 0);
+        progress.record_completion("l1", "exit0", 20);
+        progress.reach(&levels, 1);
+        assert_eq!(progress.continue_index(&levels), Some(1));
+        // going back to earlier level doesn't lose the furthest one
+        progress.reach(&levels, 0);
+        assert_eq!(progress.continue_index(&levels), Some(1));
+    }
+
+    #[test]
+    fn level_list_changes() {
+        let mut progress = Progress::default();
+        progress.record_completion("l1", "exit0", 20);
+        progress.record_completion("l2", "exit1", 30);
+        progress.furthest = Some("removed".into());
+
+        let reordered = list(&["new", "l1", "l2", "l3"]);
+        assert_eq!(progress.continue_index(&reordered), Some(3));
+        assert!(progress.is_completed("l2"));
+        assert_eq!(progress.get("l2").unwrap().exit, "exit1");
+
+        let shorter = list(&["l1", "l2"]);
+        assert_eq!(progress.continue_index(&shorter), Some(1));
+
+        let unrelated = list(&["a", "b"]);
+        assert_eq!(progress.continue_index(&unrelated), None);
+    }
+
+    #[test]
+    fn best_moves_kept() {
+        let mut progress = Progress::default();
+        progress.record_completion("l1", "exit0", 20);
+        progress.record_completion("l1", "exit1", 25);
+        assert_eq!(progress.get("l1").unwrap().best_moves, Some(20));
+        assert_eq!(progress.get("l1").unwrap().exit, "exit1");
+        progress.record_completion("l1", "exit0", 15);
+        assert_eq!(progress.get("l1").unwrap().best_moves, Some(15));
+    }
+}
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use crate::storage;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Settings {
+    /// Allow skipping levels which haven't been completed yet.
+    #[serde(default)]
+    pub allow_skip: bool,
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        storage::config_dir().map(|dir| dir.join("settings.yaml"))
+    }
+
+    pub fn load() -> Settings {
+        Settings::path()
+            .and_then(|path| storage::load_yaml(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        match Settings::path() {
+            Some(path) => storage::save_yaml(&path, self),
+            None => Err(std::io::ErrorKind::NotFound.into())
+        }
+    }
+}
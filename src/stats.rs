@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use serde::{Serialize, Deserialize};
+
+use crate::level::Level;
+use crate::storage;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LevelStats {
+    /// Level list entry the record was made with, only informative.
+    pub name: String,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub restarts: u32,
+    #[serde(default)]
+    pub completions: u32,
+    #[serde(default)]
+    pub last_time_ms: Option<u64>,
+    #[serde(default)]
+    pub best_time_ms: Option<u64>,
+    #[serde(default)]
+    pub fewest_moves: Option<u32>,
+    #[serde(default)]
+    pub fewest_pushes: Option<u32>,
+    #[serde(default)]
+    pub exits: Vec<String>,
+}
+
+fn keep_min<T: Ord + Copy>(current: Option<T>, value: T) -> Option<T> {
+    Some(current.map_or(value, |c| c.min(value)))
+}
+
+impl LevelStats {
+    pub fn record_completion(&mut self, exit: &str, time_ms: u64, moves: u32, pushes: u32) {
+        self.completions += 1;
+        self.last_time_ms = Some(time_ms);
+        self.best_time_ms = keep_min(self.best_time_ms, time_ms);
+        self.fewest_moves = keep_min(self.fewest_moves, moves);
+        self.fewest_pushes = keep_min(self.fewest_pushes, pushes);
+        if !self.exits.iter().any(|e| e == exit) {
+            self.exits.push(exit.into());
+            self.exits.sort();
+        }
+    }
+}
+
+/// Statistics keyed by content hash of the level, so that records of a level made before it
+/// was edited don't apply to the new version.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Stats {
+    #[serde(default)]
+    pub levels: BTreeMap<String, LevelStats>,
+}
+
+impl Stats {
+    pub fn path() -> Option<PathBuf> {
+        storage::data_file("stats.yaml")
+    }
+
+    pub fn load() -> Stats {
+        Stats::path()
+            .and_then(|path| storage::load_yaml(&path).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        match Stats::path() {
+            Some(path) => storage::save_yaml(&path, self),
+            None => Err(std::io::ErrorKind::NotFound.into())
+        }
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&LevelStats> {
+        self.levels.get(hash)
+    }
+
+    pub fn entry(&mut self, hash: &str, name: &str) -> &mut LevelStats {
+        let stats = self.levels.entry(hash.into()).or_default();
+        stats.name = name.into();
+        stats
+    }
+}
+
+/// Stable content hash of the level (64 bit FNV-1a over serialized level).
+pub fn level_hash(level: &Level) -> String {
+    let data = serde_yaml::to_string(level).unwrap_or_default();
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in data.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+pub fn format_time(ms: u64) -> String {
+    format!("{}:{:02}.{}", ms / 60000, (ms / 1000) % 60, (ms / 100) % 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vecmath::V2;
+
+    #[test]
+    fn hash_follows_content() {
+        let level = Level::new(10, 10);
+        let mut edited = level.clone();
+        assert_eq!(level_hash(&level), level_hash(&edited));
+        edited.p0 = V2::make(3, 3);
+        assert_ne!(level_hash(&level), level_hash(&edited));
+    }
+
+    #[test]
+    fn records() {
+        let mut stats = LevelStats::default();
+        stats.record_completion("exit1", 5000, 40, 3);
+        stats.record_completion("exit0", 7000, 30, 5);
+        stats.record_completion("exit1", 6000, 50, 1);
+        assert_eq!(stats.completions, 3);
+        assert_eq!(stats.best_time_ms, Some(5000));
+        assert_eq!(stats.last_time_ms, Some(6000));
+        assert_eq!(stats.fewest_moves, Some(30));
+        assert_eq!(stats.fewest_pushes, Some(1));
+        assert_eq!(stats.exits, vec!["exit0".to_owned(), "exit1".to_owned()]);
+    }
+
+    #[test]
+    fn time_format() {
+        assert_eq!(format_time(0), "0:00.0");
+        assert_eq!(format_time(83_456), "1:23.4");
+    }
+}
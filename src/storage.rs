@@ -0,0 +1,72 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const APP_DIR: &str = "ggj22-kiwi";
+
+fn env_dir(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".local/share")))
+    };
+    base.map(|dir| dir.join(APP_DIR))
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env_dir("APPDATA")
+    } else {
+        env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+    };
+    base.map(|dir| dir.join(APP_DIR))
+}
+
+pub fn data_file(name: &str) -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join(name))
+}
+
+/// Replaces any characters that are not safe in file names.
+pub fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// File name for data belonging to a specific item like a level.
+pub fn slot_file_name(prefix: &str, key: &str) -> String {
+    format!("{}-{}.yaml", prefix, safe_file_name(key))
+}
+
+pub fn load_yaml<T: DeserializeOwned>(path: &Path) -> std::io::Result<T> {
+    let file = File::open(path)?;
+    serde_yaml::from_reader(file).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Writes the value to a temporary file first and then renames it over the target, so that
+/// a crash in the middle of writing never leaves a truncated file behind.
+pub fn save_yaml<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let mut tmp_name = path.file_name().ok_or(ErrorKind::InvalidInput)?.to_owned();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    {
+        let file = File::create(&tmp_path)?;
+        serde_yaml::to_writer(&file, value).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp_path, path)
+}
@@ -0,0 +1,579 @@
+use crate::level::{Cell, CellColor, Level};
+use crate::vecmath::{Rectangle, V2};
+
+/// Which attributes a cell must share with the starting cell to be filled.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FillMatch {
+    Background,
+    BackgroundAndForeground,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+impl Connectivity {
+    fn neighbours(&self) -> &'static [V2] {
+        const FOUR: [V2; 4] = [V2 { x: 1, y: 0 }, V2 { x: -1, y: 0 }, V2 { x: 0, y: 1 }, V2 { x: 0, y: -1 }];
+        const EIGHT: [V2; 8] = [V2 { x: 1, y: 0 }, V2 { x: -1, y: 0 }, V2 { x: 0, y: 1 }, V2 { x: 0, y: -1 },
+            V2 { x: 1, y: 1 }, V2 { x: -1, y: 1 }, V2 { x: 1, y: -1 }, V2 { x: -1, y: -1 }];
+        match self {
+            Connectivity::Four => &FOUR,
+            Connectivity::Eight => &EIGHT,
+        }
+    }
+}
+
+fn same_kind(a: &Cell, b: &Cell, matching: FillMatch) -> bool {
+    match matching {
+        FillMatch::Background => a.background == b.background,
+        FillMatch::BackgroundAndForeground => a.background == b.background && a.foreground == b.foreground,
+    }
+}
+
+/// Positions of all the cells connected to `start` which match it, without leaving `bounds`.
+pub fn flood_fill(level: &Level, start: V2, bounds: Rectangle, matching: FillMatch,
+                  connectivity: Connectivity) -> Vec<V2> {
+    let bounds = bounds.intersection(&level.bounds());
+    if !bounds.contains(start) {
+        return vec![];
+    }
+    let origin = level[start];
+    let index = |p: V2| ((p.y - bounds.top()) * bounds.width() + p.x - bounds.left()) as usize;
+    let mut visited = vec![false; (bounds.width() * bounds.height()) as usize];
+    let mut result = vec![];
+    let mut stack = vec![start];
+    visited[index(start)] = true;
+    while let Some(pos) = stack.pop() {
+        result.push(pos);
+        for dir in connectivity.neighbours() {
+            let next = pos + *dir;
+            if bounds.contains(next) && !visited[index(next)] && same_kind(&level[next], &origin, matching) {
+                visited[index(next)] = true;
+                stack.push(next);
+            }
+        }
+    }
+    result
+}
+
+/// Cells of a straight line from `a` to `b` (Bresenham), both ends included.
+pub fn line_points(a: V2, b: V2) -> Vec<V2> {
+    let dx = (b.x - a.x).abs();
+    let dy = -(b.y - a.y).abs();
+    let step = V2::make((b.x - a.x).signum(), (b.y - a.y).signum());
+    let mut error = dx + dy;
+    let mut pos = a;
+    let mut result = vec![pos];
+    while pos != b {
+        let e2 = 2 * error;
+        if e2 >= dy {
+            error += dy;
+            pos.x += step.x;
+        }
+        if e2 <= dx {
+            error += dx;
+            pos.y += step.y;
+        }
+        result.push(pos);
+    }
+    result
+}
+
+pub fn rect_outline_points(rect: Rectangle) -> Vec<V2> {
+    let rect = rect.normalized();
+    let mut result = vec![];
+    for y in rect.top()..=rect.bottom() {
+        for x in rect.left()..=rect.right() {
+            if x == rect.left() || x == rect.right() || y == rect.top() || y == rect.bottom() {
+                result.push(V2::make(x, y));
+            }
+        }
+    }
+    result
+}
+
+pub fn ellipse_points(rect: Rectangle) -> Vec<V2> {
+    let rect = rect.normalized();
+    let rx = rect.width() as f64 / 2.0;
+    let ry = rect.height() as f64 / 2.0;
+    let cx = rect.left() as f64 + rx;
+    let cy = rect.top() as f64 + ry;
+    let mut result = vec![];
+    for y in rect.top()..=rect.bottom() {
+        for x in rect.left()..=rect.right() {
+            let dx = (x as f64 + 0.5 - cx) / rx;
+            let dy = (y as f64 + 0.5 - cy) / ry;
+            if dx * dx + dy * dy <= 1.0 {
+                result.push(V2::make(x, y));
+            }
+        }
+    }
+    result
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Shape {
+    Line,
+    Rectangle,
+    Ellipse,
+}
+
+impl Shape {
+    pub fn points(&self, a: V2, b: V2) -> Vec<V2> {
+        match self {
+            Shape::Line => line_points(a, b),
+            Shape::Rectangle => rect_outline_points(Rectangle::from_corners(a, b)),
+            Shape::Ellipse => ellipse_points(Rectangle::from_corners(a, b)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Transform {
+    FlipHorizontal,
+    FlipVertical,
+    /// Clockwise by 90°, only valid for square areas.
+    Rotate,
+}
+
+impl Transform {
+    pub fn apply(&self, rect: Rectangle, pos: V2) -> V2 {
+        let local = pos - rect.pos;
+        let moved = match self {
+            Transform::FlipHorizontal => V2::make(rect.width() - 1 - local.x, local.y),
+            Transform::FlipVertical => V2::make(local.x, rect.height() - 1 - local.y),
+            Transform::Rotate => V2::make(rect.height() - 1 - local.y, local.x),
+        };
+        rect.pos + moved
+    }
+}
+
+/// Mirroring of edits for levels built from two matching halves.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Symmetry {
+    Vertical,
+    Horizontal,
+    /// Mirrored through a single point, same as rotating by 180°.
+    Point,
+}
+
+impl Symmetry {
+    pub const NAMES: [&'static str; 3] = ["vertical", "horizontal", "point"];
+
+    pub fn parse(text: &str) -> Option<Symmetry> {
+        match text.to_lowercase().as_str() {
+            "vertical" | "v" => Some(Symmetry::Vertical),
+            "horizontal" | "h" => Some(Symmetry::Horizontal),
+            "point" | "p" => Some(Symmetry::Point),
+            _ => None
+        }
+    }
+
+    pub fn cycle(symmetry: Option<Symmetry>) -> Option<Symmetry> {
+        match symmetry {
+            None => Some(Symmetry::Vertical),
+            Some(Symmetry::Vertical) => Some(Symmetry::Horizontal),
+            Some(Symmetry::Horizontal) => Some(Symmetry::Point),
+            Some(Symmetry::Point) => None,
+        }
+    }
+
+    /// Position mirrored to `pos`. The axis is given in doubled coordinates so that it can lie
+    /// on a cell as well as between two cells, `axis2.x == 9` is between columns 4 and 5.
+    pub fn mirror(&self, pos: V2, axis2: V2) -> V2 {
+        match self {
+            Symmetry::Vertical => V2::make(axis2.x - pos.x, pos.y),
+            Symmetry::Horizontal => V2::make(pos.x, axis2.y - pos.y),
+            Symmetry::Point => axis2 - pos,
+        }
+    }
+
+    pub fn on_axis(&self, pos: V2, axis2: V2) -> bool {
+        let near = |p: i32, axis: i32| (2 * p - axis).abs() <= 1;
+        match self {
+            Symmetry::Vertical => near(pos.x, axis2.x),
+            Symmetry::Horizontal => near(pos.y, axis2.y),
+            Symmetry::Point => near(pos.x, axis2.x) && near(pos.y, axis2.y),
+        }
+    }
+}
+
+fn parse_color(name: &str) -> Option<CellColor> {
+    match name.to_lowercase().as_str() {
+        "white" | "w" => Some(CellColor::White),
+        "black" | "b" => Some(CellColor::Black),
+        "lightgray" | "lightgrey" | "lg" => Some(CellColor::LightGray),
+        "darkgray" | "darkgrey" | "dg" => Some(CellColor::DarkGray),
+        _ => None
+    }
+}
+
+/// Cell attributes to search for or to set, unspecified attributes match anything and are
+/// left unchanged.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct CellPattern {
+    pub letter: Option<char>,
+    pub foreground: Option<CellColor>,
+    pub background: Option<CellColor>,
+}
+
+impl CellPattern {
+    /// Parses space separated terms: a single character for the letter (`space` for an empty
+    /// cell), `fg=<color>` and `bg=<color>`.
+    pub fn parse(text: &str) -> Result<CellPattern, String> {
+        let mut pattern = CellPattern::default();
+        for term in text.split_whitespace() {
+            let mut chars = term.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                pattern.letter = Some(c);
+                continue;
+            }
+            let color = |value: &str| parse_color(value).ok_or(format!("Unknown color: {}", value));
+            match term.split_once('=') {
+                _ if term == "space" => pattern.letter = Some(' '),
+                Some(("fg", value)) => pattern.foreground = Some(color(value)?),
+                Some(("bg", value)) => pattern.background = Some(color(value)?),
+                _ => return Err(format!("Unknown pattern term: {}", term)),
+            }
+        }
+        if pattern == CellPattern::default() {
+            return Err("Empty pattern".into());
+        }
+        Ok(pattern)
+    }
+
+    pub fn matches(&self, cell: &Cell) -> bool {
+        let letter_matches = match self.letter {
+            Some(' ') => cell.empty(),
+            Some(letter) => cell.letter == letter,
+            None => true,
+        };
+        letter_matches &&
+            self.foreground.unwrap_or(cell.foreground) == cell.foreground &&
+            self.background.unwrap_or(cell.background) == cell.background
+    }
+
+    pub fn apply(&self, cell: &Cell) -> Cell {
+        let mut result = *cell;
+        if let Some(letter) = self.letter {
+            result.letter = letter;
+        }
+        if let Some(foreground) = self.foreground {
+            result.foreground = foreground;
+        }
+        if let Some(background) = self.background {
+            result.background = background;
+        }
+        result
+    }
+}
+
+pub fn find_matches(level: &Level, pattern: &CellPattern, area: Rectangle) -> Vec<V2> {
+    let area = area.intersection(&level.bounds());
+    let mut result = vec![];
+    for y in area.top()..=area.bottom() {
+        for x in area.left()..=area.right() {
+            let pos = V2::make(x, y);
+            if pattern.matches(&level[pos]) {
+                result.push(pos);
+            }
+        }
+    }
+    result
+}
+
+/// First match after `from` in reading order, wrapping around at the end of the level.
+pub fn find_next(level: &Level, pattern: &CellPattern, from: V2) -> Option<V2> {
+    let matches = find_matches(level, pattern, level.bounds());
+    let after = |p: &&V2| p.y > from.y || (p.y == from.y && p.x > from.x);
+    matches.iter().find(after).or(matches.first()).copied()
+}
+
+/// Letter changes which move the letters of the row segment from `from` up to column `right`
+/// by `amount` cells, colours stay in place. Letters pushed past `right` are dropped, cells
+/// which become free are cleared.
+pub fn shift_letters(level: &Level, from: V2, right: i32, amount: i32) -> Vec<(V2, char)> {
+    (from.x..=right)
+        .filter_map(|x| {
+            let source = V2::make(x - amount, from.y);
+            let letter = if source.x >= from.x && source.x <= right { level[source].letter } else { '\0' };
+            let pos = V2::make(x, from.y);
+            if level[pos].letter != letter { Some((pos, letter)) } else { None }
+        })
+        .collect()
+}
+
+/// Role of a cell in the game, follows the movement rules of `LevelRunner::walk`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Material {
+    Floor,
+    /// Gray background, can't be entered from base colours.
+    Wall,
+    /// Light gray letter, can be walked through.
+    Decoration,
+    Pushable,
+    /// Letter that blocks the way but can't be pushed.
+    Solid,
+}
+
+pub fn material(cell: &Cell) -> Material {
+    let is_base = |c: CellColor| c == CellColor::Black || c == CellColor::White;
+    if !is_base(cell.background) {
+        Material::Wall
+    } else if cell.empty() {
+        Material::Floor
+    } else if cell.foreground == CellColor::LightGray {
+        Material::Decoration
+    } else if is_base(cell.foreground) {
+        Material::Pushable
+    } else {
+        Material::Solid
+    }
+}
+
+/// How many level cells in each direction one minimap pixel covers so that a level of
+/// `size` fits into `max_pixels`.
+pub fn minimap_scale(size: V2, max_pixels: V2) -> i32 {
+    let scale = |cells: i32, pixels: i32| (cells + pixels - 1) / pixels.max(1);
+    scale(size.x, max_pixels.x).max(scale(size.y, max_pixels.y)).max(1)
+}
+
+/// Most common background within `rect`, ties prefer the earlier colour in the order white,
+/// black, light gray, dark gray.
+pub fn dominant_background(level: &Level, rect: Rectangle) -> CellColor {
+    const COLORS: [CellColor; 4] = [CellColor::White, CellColor::Black, CellColor::LightGray, CellColor::DarkGray];
+    let mut counts = [0; 4];
+    for y in rect.top()..=rect.bottom() {
+        for x in rect.left()..=rect.right() {
+            let background = level[V2::make(x, y)].background;
+            if let Some(i) = COLORS.iter().position(|c| *c == background) {
+                counts[i] += 1;
+            }
+        }
+    }
+    let best = (0..4).rev().max_by_key(|i| counts[*i]).unwrap_or(0);
+    COLORS[best]
+}
+
+/// Problems with a trigger which make the level behave unexpectedly.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct TriggerIssues {
+    /// Another trigger is at the same position, several exits may share an id.
+    pub duplicate: bool,
+    pub out_of_bounds: bool,
+}
+
+pub fn trigger_issues(level: &Level) -> Vec<TriggerIssues> {
+    level.triggers.iter().enumerate()
+        .map(|(i, trigger)| TriggerIssues {
+            duplicate: level.triggers.iter().enumerate()
+                .any(|(j, other)| i != j && other.pos == trigger.pos),
+            out_of_bounds: !level.contains(trigger.pos),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::CellColor;
+
+    fn level_from(rows: &[&str]) -> Level {
+        let mut level = Level::new(rows[0].len() as i32, rows.len() as i32);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let mut cell = Cell::make_empty();
+                cell.background = if c == '#' { CellColor::White } else { CellColor::Black };
+                if c == 'a' {
+                    cell.letter = 'a';
+                    cell.foreground = CellColor::LightGray;
+                }
+                level.set(V2::make(x as i32, y as i32), cell);
+            }
+        }
+        level
+    }
+
+    #[test]
+    fn fill_connectivity() {
+        let level = level_from(&[
+            "..#..",
+            "..#..",
+            "###..",
+            "...#.",
+        ]);
+        let bounds = level.bounds();
+        let four = flood_fill(&level, V2::make(0, 0), bounds, FillMatch::Background, Connectivity::Four);
+        assert_eq!(four.len(), 4);
+        let eight = flood_fill(&level, V2::make(0, 0), bounds, FillMatch::Background, Connectivity::Eight);
+        assert_eq!(eight.len(), 4);
+        let walls = flood_fill(&level, V2::make(2, 0), bounds, FillMatch::Background, Connectivity::Four);
+        assert_eq!(walls.len(), 5);
+        let walls8 = flood_fill(&level, V2::make(2, 0), bounds, FillMatch::Background, Connectivity::Eight);
+        assert_eq!(walls8.len(), 6);
+    }
+
+    #[test]
+    fn fill_foreground_and_bounds() {
+        let level = level_from(&[
+            "..a..",
+            ".....",
+        ]);
+        let all = level.bounds();
+        let bg = flood_fill(&level, V2::make(0, 0), all, FillMatch::Background, Connectivity::Four);
+        assert_eq!(bg.len(), 10);
+        let fg = flood_fill(&level, V2::make(0, 0), all, FillMatch::BackgroundAndForeground, Connectivity::Four);
+        assert_eq!(fg.len(), 9);
+        let selection = Rectangle { pos: V2::make(0, 0), size: V2::make(2, 2) };
+        let bounded = flood_fill(&level, V2::make(0, 0), selection, FillMatch::Background, Connectivity::Four);
+        assert_eq!(bounded.len(), 4);
+        let outside = flood_fill(&level, V2::make(4, 0), selection, FillMatch::Background, Connectivity::Four);
+        assert!(outside.is_empty());
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(line_points(V2::make(1, 1), V2::make(1, 1)), vec![V2::make(1, 1)]);
+        assert_eq!(line_points(V2::make(0, 0), V2::make(3, 0)).len(), 4);
+        let diagonal = line_points(V2::make(3, 3), V2::make(0, 0));
+        assert_eq!(diagonal, vec![V2::make(3, 3), V2::make(2, 2), V2::make(1, 1), V2::make(0, 0)]);
+        let steep = line_points(V2::make(0, 0), V2::make(2, 5));
+        assert_eq!(steep.len(), 6);
+        assert_eq!(*steep.last().unwrap(), V2::make(2, 5));
+    }
+
+    #[test]
+    fn shapes() {
+        let rect = Rectangle { pos: V2::make(2, 2), size: V2::make(4, 3) };
+        assert_eq!(rect_outline_points(rect).len(), 10);
+        let flipped = Rectangle { pos: V2::make(5, 4), size: V2::make(-2, -1) };
+        assert_eq!(rect_outline_points(flipped).len(), 10);
+        let single = Rectangle { pos: V2::make(0, 0), size: V2::make(1, 1) };
+        assert_eq!(ellipse_points(single), vec![V2::make(0, 0)]);
+        let circle = ellipse_points(Rectangle { pos: V2::make(0, 0), size: V2::make(5, 5) });
+        assert!(circle.contains(&V2::make(2, 0)));
+        assert!(circle.contains(&V2::make(2, 2)));
+        assert!(!circle.contains(&V2::make(0, 0)));
+        assert_eq!(circle.len(), 21);
+    }
+
+    #[test]
+    fn trigger_checks() {
+        use crate::level::Trigger;
+        let mut level = Level::new(10, 10);
+        for (id, x) in [("exit0", 1), ("exit1", 2), ("exit2", 2), ("exit2", 12), ("exit0", 5)] {
+            level.triggers.push(Trigger { pos: V2::make(x, 1), id: id.into() });
+        }
+        let issues = trigger_issues(&level);
+        assert_eq!(issues[0], TriggerIssues::default());
+        assert!(issues[1].duplicate && issues[2].duplicate);
+        assert!(issues[3].out_of_bounds && !issues[3].duplicate);
+        // same id at different positions, like the two exits of levels/final
+        assert_eq!(issues[4], TriggerIssues::default());
+    }
+
+    #[test]
+    fn transforms() {
+        let rect = Rectangle { pos: V2::make(10, 20), size: V2::make(3, 3) };
+        let corner = V2::make(10, 20);
+        assert_eq!(Transform::FlipHorizontal.apply(rect, corner), V2::make(12, 20));
+        assert_eq!(Transform::FlipVertical.apply(rect, corner), V2::make(10, 22));
+        assert_eq!(Transform::Rotate.apply(rect, corner), V2::make(12, 20));
+        assert_eq!(Transform::Rotate.apply(rect, V2::make(12, 20)), V2::make(12, 22));
+        assert_eq!(Transform::Rotate.apply(rect, V2::make(11, 21)), V2::make(11, 21));
+        let wide = Rectangle { pos: V2::make(0, 0), size: V2::make(4, 2) };
+        assert_eq!(Transform::FlipHorizontal.apply(wide, V2::make(1, 1)), V2::make(2, 1));
+    }
+
+    #[test]
+    fn minimap() {
+        assert_eq!(minimap_scale(V2::make(250, 250), V2::make(40, 30)), 9);
+        assert_eq!(minimap_scale(V2::make(20, 10), V2::make(40, 30)), 1);
+        assert_eq!(minimap_scale(V2::make(80, 10), V2::make(40, 30)), 2);
+
+        let level = level_from(&[
+            "##.",
+            "#..",
+        ]);
+        let all = level.bounds();
+        assert_eq!(dominant_background(&level, all), CellColor::White);
+        let right = Rectangle { pos: V2::make(1, 0), size: V2::make(2, 2) };
+        assert_eq!(dominant_background(&level, right), CellColor::Black);
+        let tie = Rectangle { pos: V2::make(1, 0), size: V2::make(2, 1) };
+        assert_eq!(dominant_background(&level, tie), CellColor::White);
+    }
+
+    #[test]
+    fn materials() {
+        let mut cell = Cell::make_empty();
+        assert_eq!(material(&cell), Material::Floor);
+        cell.letter = 'a';
+        assert_eq!(material(&cell), Material::Pushable);
+        cell.foreground = CellColor::LightGray;
+        assert_eq!(material(&cell), Material::Decoration);
+        cell.foreground = CellColor::DarkGray;
+        assert_eq!(material(&cell), Material::Solid);
+        cell.background = CellColor::DarkGray;
+        assert_eq!(material(&cell), Material::Wall);
+    }
+
+    #[test]
+    fn patterns() {
+        let level = level_from(&[
+            "#a.",
+            ".a#",
+        ]);
+        let a = CellPattern::parse("a").unwrap();
+        assert_eq!(find_matches(&level, &a, level.bounds()), vec![V2::make(1, 0), V2::make(1, 1)]);
+        assert_eq!(find_next(&level, &a, V2::make(1, 0)), Some(V2::make(1, 1)));
+        assert_eq!(find_next(&level, &a, V2::make(2, 1)), Some(V2::make(1, 0)));
+
+        let walls = CellPattern::parse("bg=white").unwrap();
+        assert_eq!(find_matches(&level, &walls, level.bounds()).len(), 2);
+        let empty_floor = CellPattern::parse("space bg=Black").unwrap();
+        assert_eq!(find_matches(&level, &empty_floor, level.bounds()).len(), 2);
+
+        let recolor = CellPattern::parse("b fg=darkgray").unwrap();
+        let cell = recolor.apply(&level[V2::make(1, 0)]);
+        assert_eq!((cell.letter, cell.foreground, cell.background), ('b', CellColor::DarkGray, CellColor::Black));
+
+        assert!(CellPattern::parse("bg=purple").is_err());
+        assert!(CellPattern::parse("").is_err());
+        assert!(CellPattern::parse("abc").is_err());
+    }
+
+    #[test]
+    fn letter_shifting() {
+        let mut level = level_from(&["..a.a#", "......"]);
+        let right_shift = shift_letters(&level, V2::make(1, 0), 4, 1);
+        assert_eq!(right_shift, vec![(V2::make(2, 0), '\0'), (V2::make(3, 0), 'a'), (V2::make(4, 0), '\0')]);
+        for (pos, letter) in right_shift {
+            let mut cell = level[pos];
+            cell.letter = letter;
+            level.set(pos, cell);
+        }
+        assert_eq!(shift_letters(&level, V2::make(0, 0), 5, -1),
+                   vec![(V2::make(2, 0), 'a'), (V2::make(3, 0), '\0')]);
+        assert!(shift_letters(&level, V2::make(0, 1), 5, 1).is_empty());
+    }
+
+    #[test]
+    fn symmetry() {
+        // Level 10 wide, axis between columns 4 and 5.
+        let axis2 = V2::make(9, 6);
+        assert_eq!(Symmetry::Vertical.mirror(V2::make(1, 2), axis2), V2::make(8, 2));
+        assert_eq!(Symmetry::Horizontal.mirror(V2::make(1, 2), axis2), V2::make(1, 4));
+        assert_eq!(Symmetry::Point.mirror(V2::make(1, 2), axis2), V2::make(8, 4));
+        assert_eq!(Symmetry::Horizontal.mirror(V2::make(1, 3), axis2), V2::make(1, 3));
+        assert!(Symmetry::Vertical.on_axis(V2::make(4, 0), axis2));
+        assert!(Symmetry::Vertical.on_axis(V2::make(5, 0), axis2));
+        assert!(!Symmetry::Vertical.on_axis(V2::make(6, 0), axis2));
+        assert!(Symmetry::Point.on_axis(V2::make(5, 3), axis2));
+        assert!(!Symmetry::Point.on_axis(V2::make(5, 0), axis2));
+        assert_eq!(Symmetry::parse("H"), Some(Symmetry::Horizontal));
+        assert_eq!(Symmetry::cycle(Some(Symmetry::Point)), None);
+    }
+}
@@ -1,4 +1,5 @@
 use std::any::Any;
+use std::cmp::{max, min};
 use std::char::decode_utf16;
 use std::io::Stdout;
 use std::io::Write;
@@ -17,7 +18,7 @@ use crossterm::{
     style,
 };
 use crossterm::event::{KeyEvent, KeyModifiers};
-use crossterm::style::Attribute;
+use crossterm::style::{Attribute, Color, Stylize};
 
 use crate::vecmath::*;
 
@@ -63,6 +64,11 @@ pub trait UiWidget {
         }
         None
     }
+    /// Called when user presses Ctrl+C, return false to keep running, for example to ask
+    /// for confirmation first.
+    fn quit_requested(&mut self, _ui: &mut UiContext) -> bool {
+        true
+    }
 }
 
 pub trait DataWidget<T>: UiWidget {
@@ -77,8 +83,13 @@ pub const DEFAULT_WINDOW_SIZE: Rectangle = Rectangle {
 pub struct Menu {
     id: UiId,
     entries: Vec<String>,
+    enabled: Vec<bool>,
+    title: Option<String>,
+    overlay: bool,
     cancelable: bool,
     selected: usize,
+    scroll: usize,
+    size: V2,
     result: Option<Option<usize>>,
     need_refresh: bool,
 }
@@ -88,42 +99,160 @@ impl Menu {
         assert!(entries.len() > 0);
         Menu {
             id: context.next_id(),
+            enabled: vec![true; entries.len()],
             entries,
+            title: None,
+            overlay: false,
             cancelable,
             selected: 0,
+            scroll: 0,
+            size: DEFAULT_WINDOW_SIZE.size,
             result: None,
             need_refresh: true,
         }
     }
 
-    fn get_selected(&self) -> usize {
+    pub fn get_selected(&self) -> usize {
         return self.selected;
     }
-    fn result(&self) -> Option<Option<usize>> {
+    pub fn result(&self) -> Option<Option<usize>> {
         return self.result;
     }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.title = Some(title.into());
+    }
+
+    /// Overlay menu is drawn in a frame on top of the current screen content instead of
+    /// clearing the screen.
+    pub fn set_overlay(&mut self, overlay: bool) {
+        self.overlay = overlay;
+    }
+
+    pub fn is_overlay(&self) -> bool {
+        self.overlay
+    }
+
+    /// Disabled entries are shown but can't be selected.
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.enabled[index] = enabled;
+        if !enabled && self.selected == index {
+            self.move_selection(1);
+        }
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.entries.len() && self.enabled[index] {
+            self.selected = index;
+            self.scroll_to_selected();
+        }
+    }
+
+    /// Selects the enabled entry closest to `target`, preferring the earlier one on a tie.
+    fn select_nearest(&mut self, target: usize) {
+        let nearest = (0..self.entries.len())
+            .filter(|i| self.enabled[*i])
+            .min_by_key(|i| (i.abs_diff(target), *i));
+        if let Some(index) = nearest {
+            self.select(index);
+        }
+    }
+
+    fn move_selection(&mut self, step: isize) {
+        let count = self.entries.len() as isize;
+        let mut next = self.selected as isize;
+        for _ in 0..count {
+            next = (next + step).rem_euclid(count);
+            if self.enabled[next as usize] {
+                self.selected = next as usize;
+                break;
+            }
+        }
+        self.scroll_to_selected();
+    }
+
+    fn visible_rows(&self) -> usize {
+        let reserved = if self.title.is_some() { 4 } else { 2 } + if self.overlay { 2 } else { 0 };
+        max(1, self.size.y - reserved) as usize
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let rows = self.visible_rows();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+        self.scroll = min(self.scroll, self.entries.len().saturating_sub(rows));
+    }
 }
 
 impl UiWidget for Menu {
     fn print(&mut self, ui: &mut UiContext) -> ::std::io::Result<()> {
-        //TODO: respect size
         if !self.need_refresh() {
             return Ok(());
         }
-        queue!(
-            ui.stdout,
-            style::Print("{}{}{}"),
-            terminal::Clear(terminal::ClearType::All),
-            cursor::MoveTo(1, 1),
-            cursor::Hide
-        )?;
-        for (i, entry) in self.entries.iter().enumerate() {
-            if self.selected != i {
-                queue!(ui.stdout, style::Print(format!("({}) {}\r\n", i, entry)))?;
+        let mut lines: Vec<(String, bool)> = vec![];
+        if let Some(title) = &self.title {
+            lines.push((title.clone(), true));
+            lines.push((String::new(), true));
+        }
+        let rows = self.visible_rows();
+        let last = min(self.entries.len(), self.scroll + rows);
+        for i in self.scroll..last {
+            let more = if i == self.scroll && self.scroll > 0 {
+                "^ "
+            } else if i + 1 == last && last < self.entries.len() {
+                "v "
+            } else {
+                "  "
+            };
+            let text = if self.selected != i {
+                format!("{}({}) {}", more, i, self.entries[i])
             } else {
-                queue!(ui.stdout, style::Print(format!("> ({}) {}\r\n", i, entry)))?;
+                format!("{}> ({}) {}", more, i, self.entries[i])
+            };
+            lines.push((text, self.enabled[i]));
+        }
+
+        let max_width = max(0, self.size.x - 4) as usize;
+        // Reserve space for the selection marker, so that the width doesn't change when moving around.
+        let entry_width = self.entries.iter().enumerate()
+            .map(|(i, entry)| format!("  > ({}) {}", i, entry).chars().count());
+        let width = lines.iter().map(|(text, _)| text.chars().count())
+            .chain(entry_width)
+            .max()
+            .map_or(0, |width| min(width, max_width));
+        let mut pos = V2::make(1, 1);
+        queue!(ui.stdout, style::ResetColor, cursor::Hide)?;
+        if self.overlay {
+            // Draw a frame in the middle of screen, leaving whatever is behind it visible.
+            let outer = V2::make(width as i32 + 4, lines.len() as i32 + 2);
+            pos = V2::make(max(0, (self.size.x - outer.x) / 2), max(0, (self.size.y - outer.y) / 2));
+            let border = format!("+{}+", "-".repeat(width + 2));
+            ui.goto(pos)?;
+            queue!(ui.stdout, style::Print(&border))?;
+            ui.goto(pos + V2::make(0, outer.y - 1))?;
+            queue!(ui.stdout, style::Print(&border))?;
+            for i in 0..lines.len() {
+                ui.goto(pos + V2::make(0, 1 + i as i32))?;
+                queue!(ui.stdout, style::Print("|"), cursor::MoveRight(width as u16 + 2), style::Print("|"))?;
             }
+            pos = pos + V2::make(2, 1);
+        } else {
+            queue!(ui.stdout, terminal::Clear(terminal::ClearType::All))?;
         }
+        for (i, (text, enabled)) in lines.iter().enumerate() {
+            let text = format!("{:<width$}", text.chars().take(width).collect::<String>(), width = width);
+            ui.goto(pos + V2::make(0, i as i32))?;
+            if *enabled {
+                queue!(ui.stdout, style::Print(text))?;
+            } else {
+                queue!(ui.stdout, style::PrintStyledContent(text.with(Color::DarkGrey)))?;
+            }
+        }
+        ui.stdout.flush()?;
+        self.need_refresh = false;
         Ok(())
     }
 
@@ -131,30 +260,34 @@ impl UiWidget for Menu {
         self.mark_refresh(true);
         match e {
             Event::Key(KeyEvent { code: KeyCode::Down, modifiers: KeyModifiers::NONE }) => {
-                self.selected += 1;
-                if self.selected >= self.entries.len() {
-                    self.selected = 0;
-                }
+                self.move_selection(1);
                 self.event(UiEventType::Changed)
             }
             Event::Key(KeyEvent { code: KeyCode::Up, modifiers: KeyModifiers::NONE }) => {
-                self.selected = if self.selected > 0 {
-                    self.selected - 1
-                } else {
-                    self.entries.len() - 1
-                };
+                self.move_selection(-1);
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::PageDown, modifiers: KeyModifiers::NONE }) => {
+                let target = min(self.selected + self.visible_rows(), self.entries.len() - 1);
+                self.select_nearest(target);
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::PageUp, modifiers: KeyModifiers::NONE }) => {
+                let target = self.selected.saturating_sub(self.visible_rows());
+                self.select_nearest(target);
                 self.event(UiEventType::Changed)
             }
             Event::Key(KeyEvent { code: KeyCode::Char(number @ '0'..='9'), modifiers: KeyModifiers::NONE }) =>
             //Event::Key(Key::Char(number @ '0'...'9')) =>
                 {
                     let n = number.to_digit(10).unwrap() as usize;
-                    if n < self.entries.len() {
-                        self.selected = n;
-                    }
+                    self.select(n);
                     self.event(UiEventType::Changed)
                 }
-            event if event == &Event::Key(KeyCode::Char('\n').into()) => {
+            Event::Key(KeyEvent { code: KeyCode::Enter, modifiers: KeyModifiers::NONE }) => {
+                if !self.enabled[self.selected] {
+                    return None;
+                }
                 self.result = Some(Some(self.selected));
                 self.event(UiEventType::Result(Box::new(self.selected)))
             }
@@ -175,7 +308,372 @@ impl UiWidget for Menu {
         self.id
     }
 
-    fn resize(&mut self, _widget_size: &Rectangle) {}
+    fn resize(&mut self, widget_size: &Rectangle) {
+        self.size = widget_size.size;
+        self.scroll_to_selected();
+        self.mark_refresh(true);
+    }
+
+    fn child_widgets(&self) -> Vec<&dyn UiWidget> {
+        Vec::new()
+    }
+
+    fn child_widgets_mut(&mut self) -> Vec<&mut dyn UiWidget> {
+        Vec::new()
+    }
+
+    fn mark_refresh(&mut self, value: bool) {
+        self.need_refresh = value
+    }
+
+    fn need_refresh(&self) -> bool {
+        self.need_refresh
+    }
+}
+
+pub struct TextInput {
+    id: UiId,
+    prompt: String,
+    text: Vec<char>,
+    cursor: usize,
+    /// Earlier entries, browsed with up/down arrows.
+    history: Vec<String>,
+    history_pos: Option<usize>,
+    /// Text typed before browsing history.
+    draft: String,
+    completer: Option<fn(&str) -> Vec<String>>,
+    /// Candidates cycled by repeated tab presses.
+    completions: Vec<String>,
+    completion_index: usize,
+    size: V2,
+    need_refresh: bool,
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let first = match words.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut prefix: String = first.clone();
+    for word in &words[1..] {
+        while !word.starts_with(&prefix) {
+            prefix.pop();
+        }
+    }
+    prefix
+}
+
+impl TextInput {
+    pub fn new(prompt: &str, text: &str, context: &mut UiContext) -> TextInput {
+        let text: Vec<char> = text.chars().collect();
+        let size = context.buffer_size();
+        TextInput {
+            id: context.next_id(),
+            prompt: prompt.into(),
+            cursor: text.len(),
+            text,
+            history: vec![],
+            history_pos: None,
+            draft: String::new(),
+            completer: None,
+            completions: vec![],
+            completion_index: 0,
+            size: V2::from(size),
+            need_refresh: true,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        self.cursor = self.text.len();
+        self.need_refresh = true;
+    }
+
+    pub fn set_history(&mut self, history: Vec<String>) {
+        self.history = history;
+        self.history_pos = None;
+    }
+
+    /// `completer` returns all the possible completions of the whole text.
+    pub fn set_completer(&mut self, completer: fn(&str) -> Vec<String>) {
+        self.completer = Some(completer);
+    }
+
+    fn browse_history(&mut self, step: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() as isize - 1;
+        let next = match self.history_pos {
+            None if step < 0 => {
+                self.draft = self.text();
+                last
+            }
+            None => return,
+            Some(pos) => pos as isize + step,
+        };
+        if next > last {
+            self.history_pos = None;
+            let draft = self.draft.clone();
+            self.set_text(&draft);
+        } else {
+            let next = max(next, 0) as usize;
+            self.history_pos = Some(next);
+            let entry = self.history[next].clone();
+            self.set_text(&entry);
+        }
+    }
+
+    fn complete(&mut self) {
+        if !self.completions.is_empty() {
+            self.completion_index = (self.completion_index + 1) % self.completions.len();
+            let next = self.completions[self.completion_index].clone();
+            self.set_text(&next);
+            return;
+        }
+        let candidates = match self.completer {
+            Some(completer) => completer(&self.text()),
+            None => return,
+        };
+        let prefix = common_prefix(&candidates);
+        if candidates.len() == 1 || prefix.chars().count() > self.text.len() {
+            self.set_text(&prefix);
+        } else if candidates.len() > 1 {
+            self.set_text(&candidates[0]);
+            self.completions = candidates;
+            self.completion_index = 0;
+        }
+    }
+}
+
+impl UiWidget for TextInput {
+    fn print(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        if !self.need_refresh() {
+            return Ok(());
+        }
+        let row = max(0, self.size.y - 1);
+        let width = max(1, self.size.x - self.prompt.chars().count() as i32 - 1) as usize;
+        // Scroll long text so that the cursor stays visible.
+        let start = (self.cursor + 1).saturating_sub(width);
+        let visible: String = self.text.iter().skip(start).take(width).collect();
+        ui.goto(V2::make(0, row))?;
+        queue!(ui.stdout, style::ResetColor,
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            style::PrintStyledContent(self.prompt.as_str().with(Color::Yellow)),
+            style::Print(visible))?;
+        let cursor_x = self.prompt.chars().count() + self.cursor - start;
+        ui.goto(V2::make(cursor_x as i32, row))?;
+        queue!(ui.stdout, cursor::SetCursorShape(cursor::CursorShape::Line), cursor::Show)?;
+        ui.stdout.flush()?;
+        self.need_refresh = false;
+        Ok(())
+    }
+
+    fn input(&mut self, e: &Event, _ui: &mut UiContext) -> Option<UiEvent> {
+        self.mark_refresh(true);
+        if let Event::Key(KeyEvent { code, .. }) = e {
+            if *code != KeyCode::Tab {
+                self.completions.clear();
+            }
+        }
+        match e {
+            Event::Key(KeyEvent { code: KeyCode::Enter, modifiers: KeyModifiers::NONE }) => {
+                self.event(UiEventType::Result(Box::new(self.text())))
+            }
+            Event::Key(KeyEvent { code: KeyCode::Up, modifiers: KeyModifiers::NONE }) => {
+                self.browse_history(-1);
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Down, modifiers: KeyModifiers::NONE }) => {
+                self.browse_history(1);
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Tab, modifiers: KeyModifiers::NONE }) => {
+                self.complete();
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }) => {
+                self.event(UiEventType::Canceled)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Left, modifiers: KeyModifiers::NONE }) => {
+                self.cursor = self.cursor.saturating_sub(1);
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Right, modifiers: KeyModifiers::NONE }) => {
+                self.cursor = min(self.cursor + 1, self.text.len());
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Home, modifiers: KeyModifiers::NONE }) => {
+                self.cursor = 0;
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::End, modifiers: KeyModifiers::NONE }) => {
+                self.cursor = self.text.len();
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Backspace, modifiers: KeyModifiers::NONE }) |
+            Event::Key(KeyEvent { code: KeyCode::Char('h'), modifiers: KeyModifiers::CONTROL }) => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.text.remove(self.cursor);
+                }
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Delete, modifiers: KeyModifiers::NONE }) => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                }
+                self.event(UiEventType::Changed)
+            }
+            Event::Key(KeyEvent { code: KeyCode::Char(c), modifiers: m }) if
+            !c.is_control() && (m == &KeyModifiers::NONE || m == &KeyModifiers::SHIFT) => {
+                self.text.insert(self.cursor, *c);
+                self.cursor += 1;
+                self.event(UiEventType::Changed)
+            }
+            _ => None,
+        }
+    }
+
+    fn get_id(&self) -> UiId {
+        self.id
+    }
+
+    fn resize(&mut self, widget_size: &Rectangle) {
+        self.size = widget_size.size;
+        self.mark_refresh(true);
+    }
+
+    fn child_widgets(&self) -> Vec<&dyn UiWidget> {
+        Vec::new()
+    }
+
+    fn child_widgets_mut(&mut self) -> Vec<&mut dyn UiWidget> {
+        Vec::new()
+    }
+
+    fn mark_refresh(&mut self, value: bool) {
+        self.need_refresh = value
+    }
+
+    fn need_refresh(&self) -> bool {
+        self.need_refresh
+    }
+}
+
+pub struct TextView {
+    id: UiId,
+    title: String,
+    lines: Vec<String>,
+    scroll: usize,
+    size: V2,
+    need_refresh: bool,
+}
+
+impl TextView {
+    pub fn new(title: &str, lines: Vec<String>, context: &mut UiContext) -> TextView {
+        TextView {
+            id: context.next_id(),
+            title: title.into(),
+            lines,
+            scroll: 0,
+            size: DEFAULT_WINDOW_SIZE.size,
+            need_refresh: true,
+        }
+    }
+
+    fn visible_rows(&self) -> usize {
+        // frame, title with the empty line below it and the scroll position footer
+        max(1, self.size.y - 6) as usize
+    }
+
+    fn scroll_by(&mut self, step: isize) {
+        let last = self.lines.len().saturating_sub(self.visible_rows());
+        self.scroll = min((self.scroll as isize + step).max(0) as usize, last);
+    }
+}
+
+impl UiWidget for TextView {
+    fn print(&mut self, ui: &mut UiContext) -> std::io::Result<()> {
+        if !self.need_refresh() {
+            return Ok(());
+        }
+        let rows = self.visible_rows();
+        let last = min(self.lines.len(), self.scroll + rows);
+        let footer = if self.lines.len() > rows {
+            format!("{}-{} of {}, arrows/page keys scroll, esc closes", self.scroll + 1, last, self.lines.len())
+        } else {
+            "esc closes".into()
+        };
+        let mut lines = vec![self.title.clone(), String::new()];
+        lines.extend(self.lines[self.scroll..last].iter().cloned());
+        lines.push(footer);
+
+        let max_width = max(0, self.size.x - 4) as usize;
+        let width = lines.iter().chain(self.lines.iter())
+            .map(|line| line.chars().count())
+            .max()
+            .map_or(0, |width| min(width, max_width));
+        let outer = V2::make(width as i32 + 4, lines.len() as i32 + 2);
+        let pos = V2::make(max(0, (self.size.x - outer.x) / 2), max(0, (self.size.y - outer.y) / 2));
+        let border = format!("+{}+", "-".repeat(width + 2));
+        queue!(ui.stdout, style::ResetColor, cursor::Hide)?;
+        ui.goto(pos)?;
+        queue!(ui.stdout, style::Print(&border))?;
+        ui.goto(pos + V2::make(0, outer.y - 1))?;
+        queue!(ui.stdout, style::Print(&border))?;
+        for (i, line) in lines.iter().enumerate() {
+            let text = format!("{:<width$}", line.chars().take(width).collect::<String>(), width = width);
+            ui.goto(pos + V2::make(0, 1 + i as i32))?;
+            queue!(ui.stdout, style::Print("| "))?;
+            if i == 0 {
+                queue!(ui.stdout, style::PrintStyledContent(text.with(Color::Yellow)))?;
+            } else if i + 1 == lines.len() {
+                queue!(ui.stdout, style::PrintStyledContent(text.with(Color::DarkGrey)))?;
+            } else {
+                queue!(ui.stdout, style::Print(text))?;
+            }
+            queue!(ui.stdout, style::Print(" |"))?;
+        }
+        ui.stdout.flush()?;
+        self.need_refresh = false;
+        Ok(())
+    }
+
+    fn input(&mut self, e: &Event, _ui: &mut UiContext) -> Option<UiEvent> {
+        self.mark_refresh(true);
+        let page = self.visible_rows() as isize;
+        match e {
+            Event::Key(KeyEvent { code: KeyCode::Down, modifiers: KeyModifiers::NONE }) => self.scroll_by(1),
+            Event::Key(KeyEvent { code: KeyCode::Up, modifiers: KeyModifiers::NONE }) => self.scroll_by(-1),
+            Event::Key(KeyEvent { code: KeyCode::PageDown, modifiers: KeyModifiers::NONE }) |
+            Event::Key(KeyEvent { code: KeyCode::Char(' '), modifiers: KeyModifiers::NONE }) => self.scroll_by(page),
+            Event::Key(KeyEvent { code: KeyCode::PageUp, modifiers: KeyModifiers::NONE }) => self.scroll_by(-page),
+            Event::Key(KeyEvent { code: KeyCode::Home, modifiers: KeyModifiers::NONE }) => self.scroll = 0,
+            Event::Key(KeyEvent { code: KeyCode::End, modifiers: KeyModifiers::NONE }) => self.scroll_by(self.lines.len() as isize),
+            Event::Key(KeyEvent { code: KeyCode::Esc, modifiers: KeyModifiers::NONE }) |
+            Event::Key(KeyEvent { code: KeyCode::Enter, modifiers: KeyModifiers::NONE }) |
+            Event::Key(KeyEvent { code: KeyCode::Char('q'), modifiers: KeyModifiers::NONE }) => {
+                return self.event(UiEventType::Canceled);
+            }
+            _ => return None,
+        }
+        self.event(UiEventType::Changed)
+    }
+
+    fn get_id(&self) -> UiId {
+        self.id
+    }
+
+    fn resize(&mut self, widget_size: &Rectangle) {
+        self.size = widget_size.size;
+        self.scroll_by(0);
+        self.mark_refresh(true);
+    }
 
     fn child_widgets(&self) -> Vec<&dyn UiWidget> {
         Vec::new()
@@ -256,7 +754,9 @@ impl<'a> UiContext<'a> {
                                            code: KeyCode::Char('c'),
                                            modifiers: KeyModifiers::CONTROL
                                        }) => {
-                                return Ok(());
+                                if widget.quit_requested(self) {
+                                    return Ok(());
+                                }
                             }
                             _ => {
                                 let r = widget.input(&event, self);
@@ -307,7 +807,65 @@ impl<'a> UiContext<'a> {
     pub fn restore_normal(&mut self) {
         execute!(self.stdout, crossterm::terminal::LeaveAlternateScreen);
         disable_raw_mode();
-        execute!(self.stdout, crossterm::cursor::Show, style::ResetColor, style::SetAttribute(Attribute::Reset), crossterm::event::DisableMouseCapture);
+        execute!(self.stdout, crossterm::cursor::Show, cursor::SetCursorShape(cursor::CursorShape::Block), style::ResetColor, style::SetAttribute(Attribute::Reset), crossterm::event::DisableMouseCapture);
         execute!(self.stdout, cursor::MoveToNextLine(1));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu(count: usize, rows: i32) -> Menu {
+        let mut stdout = std::io::stdout();
+        let mut ui = UiContext::create(&mut stdout).unwrap();
+        let mut menu = Menu::new((0..count).map(|i| format!("entry {}", i)).collect(), true, &mut ui);
+        menu.resize(&Rectangle { pos: V2::new(), size: V2::make(20, rows + 2) });
+        menu
+    }
+
+    fn key(menu: &mut Menu, code: KeyCode) {
+        let mut stdout = std::io::stdout();
+        let mut ui = UiContext::create(&mut stdout).unwrap();
+        menu.input(&Event::Key(KeyEvent { code, modifiers: KeyModifiers::NONE }), &mut ui);
+    }
+
+    #[test]
+    fn scroll_follows_selection() {
+        let mut menu = menu(10, 4);
+        for _ in 0..5 {
+            key(&mut menu, KeyCode::Down);
+        }
+        assert_eq!((menu.selected, menu.scroll), (5, 2));
+        key(&mut menu, KeyCode::PageDown);
+        assert_eq!((menu.selected, menu.scroll), (9, 6));
+        key(&mut menu, KeyCode::Down);
+        assert_eq!((menu.selected, menu.scroll), (0, 0));
+        key(&mut menu, KeyCode::Up);
+        assert_eq!((menu.selected, menu.scroll), (9, 6));
+    }
+
+    #[test]
+    fn disabled_entries_are_skipped() {
+        let mut menu = menu(10, 4);
+        menu.set_enabled(0, false);
+        assert_eq!(menu.selected, 1);
+        menu.set_enabled(2, false);
+        key(&mut menu, KeyCode::Down);
+        assert_eq!(menu.selected, 3);
+        for i in 5..10 {
+            menu.set_enabled(i, false);
+        }
+        key(&mut menu, KeyCode::PageDown);
+        assert_eq!(menu.selected, 4);
+        key(&mut menu, KeyCode::Down);
+        assert_eq!(menu.selected, 1);
+        key(&mut menu, KeyCode::PageUp);
+        assert_eq!(menu.selected, 1);
+        menu.set_enabled(8, true);
+        key(&mut menu, KeyCode::PageDown);
+        assert_eq!(menu.selected, 4);
+        key(&mut menu, KeyCode::PageDown);
+        assert_eq!(menu.selected, 8);
+    }
+}
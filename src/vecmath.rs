@@ -1,10 +1,11 @@
+use std::cmp::{max, min};
 use std::ops::{Add, Sub};
 use crossterm::terminal::size;
 
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize)]
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub struct V2 {
     pub x: i32,
     pub y: i32,
@@ -103,6 +104,27 @@ impl Rectangle {
             pos.y >= self.top() && pos.y <= self.bottom()
     }
 
+    /// Smallest rectangle containing both corners.
+    pub fn from_corners(a: V2, b: V2) -> Rectangle {
+        let pos = V2::make(min(a.x, b.x), min(a.y, b.y));
+        Rectangle {
+            pos,
+            size: V2::make(max(a.x, b.x) - pos.x + 1, max(a.y, b.y) - pos.y + 1),
+        }
+    }
+
+    /// Overlapping part of both rectangles, has zero size when they don't overlap.
+    pub fn intersection(&self, other: &Rectangle) -> Rectangle {
+        let left = max(self.left(), other.left());
+        let top = max(self.top(), other.top());
+        let right = min(self.right(), other.right());
+        let bottom = min(self.bottom(), other.bottom());
+        Rectangle {
+            pos: V2::make(left, top),
+            size: V2::make(max(right - left + 1, 0), max(bottom - top + 1, 0)),
+        }
+    }
+
     pub fn normalized(&self) -> Rectangle {
         if self.size.x > 0 && self.size.y > 0 {
             return *self;
@@ -165,4 +187,14 @@ mod tests {
         assert_eq!(false, r.contains(r.bottom_right() + V2::make(1, 0)));
         assert_eq!(false, r.contains(r.bottom_right() + V2::make(0, 1)));
     }
+
+    #[test]
+    fn intersection() {
+        let a = Rectangle { pos: V2::make(0, 0), size: V2::make(5, 5) };
+        let b = Rectangle { pos: V2::make(3, -2), size: V2::make(4, 4) };
+        let r = a.intersection(&b);
+        assert_eq!((r.pos, r.size), (V2::make(3, 0), V2::make(2, 2)));
+        let c = Rectangle { pos: V2::make(10, 10), size: V2::make(1, 1) };
+        assert_eq!(a.intersection(&c).width(), 0);
+    }
 }